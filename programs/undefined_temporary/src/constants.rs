@@ -2,3 +2,67 @@ use anchor_lang::prelude::*;
 
 // #[constant]
 // pub const SEED: &str = "anchor";
+
+#[constant]
+pub const MAX_CASE_REFERENCE_LENGTH: usize = 64;
+
+// Name of the list a denied address comes from (e.g. a sanctions list)
+#[constant]
+pub const MAX_LIST_SOURCE_LENGTH: usize = 32;
+
+// Addresses imported in the deny list by one instruction
+#[constant]
+pub const MAX_DENY_LIST_IMPORT: usize = 10;
+
+// Balance cap tiers per mint (see BalanceCaps)
+#[constant]
+pub const MAX_BALANCE_CAP_TIERS: usize = 8;
+
+#[constant]
+pub const MAX_ROLE_ASSIGNMENTS: usize = 32;
+
+#[constant]
+pub const MAX_PAUSE_EXEMPTIONS: usize = 10;
+
+#[constant]
+pub const MAX_REQUIRED_CLAIMS: usize = 8;
+
+// Countries of the jurisdiction matrix, one bit each in a u64
+#[constant]
+pub const MAX_JURISDICTIONS: usize = 64;
+
+// Country claims a sub-issuer can be restricted to
+#[constant]
+pub const MAX_SCOPE_JURISDICTIONS: usize = 8;
+
+// Previous signing keys kept by an issuer record after rotations
+#[constant]
+pub const MAX_ISSUER_KEY_HISTORY: usize = 4;
+
+// Keys allowed to hold tokens for an institution
+#[constant]
+pub const MAX_AUTHORIZED_SIGNERS: usize = 10;
+
+// Program-owned vaults an institution can link
+#[constant]
+pub const MAX_INSTITUTION_VAULTS: usize = 10;
+
+// One proven claim per claim type
+#[constant]
+pub const MAX_PROVEN_CLAIMS: usize = 6;
+
+// Seconds a claim proven with a Merkle proof can be used by the transfer hook
+#[constant]
+pub const CLAIM_PROOF_VALIDITY: i64 = 600;
+
+// Version of the transfer hook accounts layout (see extra_account_metas)
+#[constant]
+pub const EXTRA_ACCOUNT_METAS_VERSION: u16 = 13;
+
+// Reason code of an issuer marked inactive by prune_expired_identity once expired, reserved to the crank
+#[constant]
+pub const EXPIRED_REASON_CODE: u16 = u16::MAX;
+
+// Share of the rent of a pruned idendity paid to the cranker, in basis points
+#[constant]
+pub const PRUNE_BOUNTY_BPS: u64 = 500;
@@ -1,7 +1,24 @@
 use anchor_lang::prelude::*;
 
+// Validity durations given by the issuers to idendities, claims and commitments
 #[error_code]
-pub enum ErrorCode {
-    #[msg("Custom error message")]
-    CustomError,
+pub enum ValidityError {
+    #[msg("The validity duration must be positive")]
+    NonPositiveValidityDuration,
+    #[msg("The validity duration overflows the expiration timestamp")]
+    ValidityDurationOverflow,
+    #[msg("The validity duration exceeds the maximum of the issuer")]
+    AboveIssuerMaxValidity,
+    #[msg("The validity duration is below the minimum of the mint")]
+    BelowMintMinValidity,
+    #[msg("The validity duration exceeds the maximum of the mint")]
+    AboveMintMaxValidity,
+    #[msg("Invalid validity bounds: negative, or minimum above maximum")]
+    InvalidValidityBounds,
+}
+
+// Expiration of an attestation made at `now` for `validity_duration`
+pub fn expires_after(now: i64, validity_duration: i64) -> Result<i64> {
+    now.checked_add(validity_duration)
+        .ok_or(ValidityError::ValidityDurationOverflow.into())
 }
@@ -21,6 +21,17 @@ pub struct AddTokenAccount<'info> {
 
 pub fn _add_token_account(ctx: Context<AddTokenAccount>) -> Result<()>  {
 
+    let seeds :&[&[&[u8]]] = &[&[b"mint",&[ctx.bumps.mint]]];
+
+    // The account is created frozen (DefaultAccountState), and the authority cannot be set on a frozen account
+    // so we thaw it for the time of the set_authority and freeze it back until an idendity is issued
+    thaw_with_mint(
+        &ctx.accounts.token_account.to_account_info(),
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        ctx.bumps.mint,
+    )?;
+
     let ix = spl_token_2022::instruction::set_authority(
         &spl_token_2022::id(),
         &ctx.accounts.token_account.key(),
@@ -30,16 +41,57 @@ pub fn _add_token_account(ctx: Context<AddTokenAccount>) -> Result<()>  {
         &[],
     )?;
 
-    let seeds :&[&[&[u8]]] = &[&[b"mint",&[ctx.bumps.mint]]];  
-
     program::invoke_signed(&ix, &[
         ctx.accounts.token_account.to_account_info(),
         ctx.accounts.mint.to_account_info(),
         ctx.accounts.owner.to_account_info(),
         ctx.accounts.token_program.to_account_info(),
     ],
-        seeds)?;    
+        seeds)?;
+
+    freeze_with_mint(
+        &ctx.accounts.token_account.to_account_info(),
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        ctx.bumps.mint,
+    )?;
+
+    Ok(())
+}
+
+// The mint PDA is the freeze authority of the token accounts: every thaw and freeze of the program goes through these two helpers
+#[inline(always)]
+pub fn thaw_with_mint<'info>(token_account: &AccountInfo<'info>, mint: &AccountInfo<'info>, token_program: &AccountInfo<'info>, bump: u8) -> Result<()> {
+    let ix = spl_token_2022::instruction::thaw_account(
+        &spl_token_2022::id(),
+        token_account.key,
+        mint.key,
+        mint.key,
+        &[],
+    )?;
 
+    program::invoke_signed(
+        &ix,
+        &[token_account.clone(), mint.clone(), token_program.clone()],
+        &[&[b"mint", &[bump]]],
+    )?;
+    Ok(())
+}
 
+#[inline(always)]
+pub fn freeze_with_mint<'info>(token_account: &AccountInfo<'info>, mint: &AccountInfo<'info>, token_program: &AccountInfo<'info>, bump: u8) -> Result<()> {
+    let ix = spl_token_2022::instruction::freeze_account(
+        &spl_token_2022::id(),
+        token_account.key,
+        mint.key,
+        mint.key,
+        &[],
+    )?;
+
+    program::invoke_signed(
+        &ix,
+        &[token_account.clone(), mint.clone(), token_program.clone()],
+        &[&[b"mint", &[bump]]],
+    )?;
     Ok(())
-}
\ No newline at end of file
+}
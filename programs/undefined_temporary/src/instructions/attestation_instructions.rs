@@ -0,0 +1,214 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        ed25519_program,
+        sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
+};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{frozen_by_compliance, set_link, thaw_with_mint, Claim, ClaimType, IdAccount, IdentityLink, Issuer, IssuerError, IssuerRecord, MintConfig};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AttestedClaim {
+    pub claim_type: ClaimType,
+    pub value: u16,
+    pub expires_at: i64,
+}
+
+// Message signed off-chain by the issuer (borsh serialized), checked against the Ed25519 instruction preceding add_attested_idendity.
+// It can be submitted only once: the nonce, chosen by the issuer, is consumed (see UsedAttestation).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct IdendityAttestation {
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+    pub owner: Pubkey,
+    pub expires_at: i64,
+    pub nonce: u64,
+    pub claims: Vec<AttestedClaim>,
+}
+
+// One per submitted attestation, never closed: a pruned idendity can't be recreated by replaying its attestation
+#[account]
+pub struct UsedAttestation {
+    pub token_account: Pubkey, // 32
+    pub used_at: i64,          // 8
+}
+
+impl UsedAttestation {
+    pub const SPACE: usize = 8 + 32 + 8;
+}
+
+// The user submits the attestation of the issuer and pays the rent
+#[derive(Accounts)]
+#[instruction(attestation: IdendityAttestation)]
+pub struct InitializeAttestedId<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.active @ IssuerError::IssuerNotActive)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    // Root issuer of a sub-issuer
+    #[account(seeds = [b"issuer", issuer_record.parent.as_ref()], bump)]
+    pub parent_issuer_record: Option<Account<'info, IssuerRecord>>,
+    #[account(init, seeds = [b"identity", token_account.key().as_ref()], bump, payer = owner, space = IdAccount::space(1, 0, attestation.claims.len()))]
+    pub idendity: Account<'info, IdAccount>,
+    #[account(init, seeds = [b"used_attestation", issuer_record.id.as_ref(), attestation.nonce.to_le_bytes().as_ref()], bump, payer = owner, space = UsedAttestation::SPACE)]
+    pub used_attestation: Account<'info, UsedAttestation>,
+    // The token account is linked to its own idendity, replacing a link to a person idendity
+    #[account(init_if_needed, seeds = [b"identity_link", token_account.key().as_ref()], bump, payer = owner, space = IdentityLink::SPACE)]
+    pub link: Account<'info, IdentityLink>,
+    /// CHECK: Holdings of the subject the token account was linked to before, may not exist (see set_link)
+    #[account(mut, seeds = [b"holdings", mint.key().as_ref(), link.subject.as_ref()], bump)]
+    pub previous_holdings: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Freeze record of the token account, may not exist (see frozen_by_compliance)
+    #[account(seeds = [b"freeze_record", token_account.key().as_ref()], bump)]
+    pub freeze_record: UncheckedAccount<'info>,
+    /// CHECK: Instructions sysvar, used to read the Ed25519 instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+pub struct AttestedIdendityAdded {
+    pub token_account: Pubkey,
+    pub issuer: Pubkey,
+    pub expires_at: i64,
+}
+
+#[error_code]
+pub enum AttestationError {
+    #[msg("The attestation does not match the accounts")]
+    AttestationMismatch,
+    #[msg("The attestation expired")]
+    AttestationExpired,
+    #[msg("The previous instruction must be an Ed25519 signature verification")]
+    MissingEd25519Instruction,
+    #[msg("The Ed25519 instruction does not verify the attestation signed by the issuer")]
+    InvalidEd25519Instruction,
+}
+
+pub fn _add_attested_idendity(ctx: Context<InitializeAttestedId>, attestation: IdendityAttestation) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    if attestation.mint != ctx.accounts.mint.key()
+        || attestation.token_account != ctx.accounts.token_account.key()
+        || attestation.owner != ctx.accounts.owner.key()
+    {
+        return Err(AttestationError::AttestationMismatch.into());
+    }
+    if attestation.expires_at < now {
+        return Err(AttestationError::AttestationExpired.into());
+    }
+    let issuer_record = &ctx.accounts.issuer_record;
+    issuer_record.check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), now)?;
+    let validity_duration = attestation.expires_at - now;
+    issuer_record.check_validity_duration(validity_duration)?;
+    ctx.accounts.mint_config.check_validity_duration(validity_duration)?;
+    for claim in attestation.claims.iter() {
+        claim.claim_type.check_value(claim.value)?;
+        issuer_record.check_claim_in_scope(claim.claim_type, claim.value)?;
+        // Same bounds as a claim set by the issuer on-chain (see set_claim)
+        issuer_record.check_validity_duration(claim.expires_at.saturating_sub(now))?;
+    }
+
+    // Signed with the current key of the issuer, the idendity references the issuer id
+    check_ed25519_instruction(
+        &ctx.accounts.instructions_sysvar,
+        &ctx.accounts.issuer_record.key,
+        &attestation.try_to_vec()?,
+    )?;
+    let issuer_key = ctx.accounts.issuer_record.id;
+
+    let idendity = &mut ctx.accounts.idendity;
+    idendity.owner = ctx.accounts.owner.key();
+    idendity.token_account = ctx.accounts.token_account.key();
+    idendity.issuers = vec![Issuer {
+        key: issuer_key,
+        last_modified: now,
+        expires_at: attestation.expires_at,
+        active: true,
+        revoked: false,
+        reason_code: 0,
+    }];
+    idendity.recovered_token_address = vec![];
+    idendity.claims = attestation
+        .claims
+        .iter()
+        .map(|claim| Claim {
+            claim_type: claim.claim_type,
+            value: claim.value,
+            issuer: issuer_key,
+            version: 1,
+            issued_at: now,
+            expires_at: claim.expires_at,
+        })
+        .collect();
+    idendity.commitment = None;
+    idendity.payer = ctx.accounts.owner.key();
+    let used_attestation = &mut ctx.accounts.used_attestation;
+    used_attestation.token_account = ctx.accounts.token_account.key();
+    used_attestation.used_at = now;
+    // The token account leaves the person idendity it may have been linked to, its own idendity has no holdings
+    set_link(
+        &mut ctx.accounts.link,
+        ctx.accounts.token_account.key(),
+        &ctx.accounts.token_account,
+        ctx.accounts.owner.key(),
+        &ctx.accounts.previous_holdings,
+    )?;
+
+    // Token accounts are frozen by default, the attestation of the issuer unlocks the account (not a freeze ordered by Compliance)
+    if ctx.accounts.token_account.is_frozen() && !frozen_by_compliance(&ctx.accounts.freeze_record)? {
+        thaw_with_mint(
+            &ctx.accounts.token_account.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.bumps.mint,
+        )?;
+    }
+
+    emit!(AttestedIdendityAdded {
+        token_account: ctx.accounts.token_account.key(),
+        issuer: issuer_key,
+        expires_at: attestation.expires_at,
+    });
+    Ok(())
+}
+
+// The Ed25519 program verifies the signature, here we only check that it verified this message for this issuer.
+// Layout: num_signatures (1), padding (1), then the offsets (7 * u16) of the signature, public key and message.
+fn check_ed25519_instruction(instructions_sysvar: &AccountInfo, issuer: &Pubkey, message: &[u8]) -> Result<()> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    if current_index == 0 {
+        return Err(AttestationError::MissingEd25519Instruction.into());
+    }
+    let ix = load_instruction_at_checked((current_index - 1) as usize, instructions_sysvar)?;
+    if ix.program_id != ed25519_program::ID {
+        return Err(AttestationError::MissingEd25519Instruction.into());
+    }
+
+    let data = &ix.data;
+    if data.len() < 16 || data[0] != 1 {
+        return Err(AttestationError::InvalidEd25519Instruction.into());
+    }
+    let read_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]) as usize;
+    let public_key_offset = read_u16(6);
+    let message_offset = read_u16(10);
+    let message_size = read_u16(12);
+    // The signature, the public key and the message must be in the Ed25519 instruction itself
+    let in_same_instruction = [4, 8, 14].iter().all(|offset| read_u16(*offset) == u16::MAX as usize);
+
+    let public_key = data.get(public_key_offset..public_key_offset + 32);
+    let signed_message = data.get(message_offset..message_offset + message_size);
+    if !in_same_instruction || public_key != Some(issuer.as_ref()) || signed_message != Some(message) {
+        return Err(AttestationError::InvalidEd25519Instruction.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::{
+    load_link, valid_claims, ClaimType, ConfigError, IdAccount, MintConfig, ProgramConfig,
+    ProvenClaim, Role, MAX_BALANCE_CAP_TIERS,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BalanceCapTier {
+    // Total 11
+    pub claim_type: ClaimType, // 1
+    pub value: u16,            // 2
+    pub max_balance: u64,      // 8 - u64::MAX: no cap
+}
+
+// Per mint caps on the balance of an idendity, kept in MintConfig and read by the transfer hook and mint_to for the receiver.
+// The highest cap of the tiers matching a valid claim of the idendity applies (e.g. Accredited = 1),
+// the default cap applies to the idendities matching no tier (e.g. retail holders).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BalanceCaps {
+    pub default_max_balance: u64,   // 8 - u64::MAX: no cap
+    pub tiers: Vec<BalanceCapTier>, // 4 + 11 * MAX_BALANCE_CAP_TIERS
+}
+
+impl BalanceCaps {
+    pub const SPACE: usize = 8 + 4 + 11 * MAX_BALANCE_CAP_TIERS;
+
+    pub fn no_cap() -> Self {
+        BalanceCaps {
+            default_max_balance: u64::MAX,
+            tiers: vec![],
+        }
+    }
+
+    pub fn max_balance(&self, idendity: &IdAccount, proven: &[ProvenClaim], checked_issuers: &[Pubkey], now: i64) -> u64 {
+        valid_claims(idendity, proven, checked_issuers, now)
+            .filter_map(|(claim_type, value)| {
+                self.tiers
+                    .iter()
+                    .find(|tier| tier.claim_type == claim_type && tier.value == value)
+                    .map(|tier| tier.max_balance)
+            })
+            .max()
+            .unwrap_or(self.default_max_balance)
+    }
+
+    pub fn check_balance(
+        &self,
+        idendity: &IdAccount,
+        proven: &[ProvenClaim],
+        checked_issuers: &[Pubkey],
+        balance: u64,
+        now: i64,
+    ) -> Result<()> {
+        if balance > self.max_balance(idendity, proven, checked_issuers, now) {
+            return Err(BalanceCapError::BalanceCapExceeded.into());
+        }
+        Ok(())
+    }
+}
+
+// Sum of the balances of the token accounts linked to a person or institution idendity, kept up to date by the links,
+// the transfer hook, mint_to, seizures and recoveries. An idendity issued for a single token account holds the balance of that account.
+// Burns by the owner of a token account don't go through the transfer hook: the holdings then overstate the balance of the
+// idendity, which only makes its cap stricter.
+#[account]
+pub struct IdentityHoldings {
+    pub subject: Pubkey, // 32
+    pub amount: u64,     // 8
+}
+
+impl IdentityHoldings {
+    pub const SPACE: usize = 8 + 32 + 8;
+}
+
+#[derive(Accounts)]
+pub struct SetBalanceCaps<'info> {
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &compliance.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub compliance: Signer<'info>,
+}
+
+#[event]
+pub struct BalanceCapsUpdated {
+    pub mint: Pubkey,
+    pub default_max_balance: u64,
+    pub tiers: Vec<BalanceCapTier>,
+}
+
+#[error_code]
+pub enum BalanceCapError {
+    #[msg("Too many balance cap tiers")]
+    TooManyTiers,
+    #[msg("The balance of the receiver would exceed its cap")]
+    BalanceCapExceeded,
+    #[msg("The holdings are not those of the idendity the token account is linked to")]
+    WrongHoldings,
+}
+
+pub fn _set_balance_caps(ctx: Context<SetBalanceCaps>, default_max_balance: u64, tiers: Vec<BalanceCapTier>) -> Result<()> {
+    if tiers.len() > MAX_BALANCE_CAP_TIERS {
+        return Err(BalanceCapError::TooManyTiers.into());
+    }
+    ctx.accounts.mint_config.balance_caps = BalanceCaps {
+        default_max_balance,
+        tiers: tiers.clone(),
+    };
+
+    emit!(BalanceCapsUpdated {
+        mint: ctx.accounts.mint.key(),
+        default_max_balance,
+        tiers,
+    });
+    Ok(())
+}
+
+// Holdings only exist for person and institution idendities
+pub fn load_holdings(account: &AccountInfo) -> Result<Option<IdentityHoldings>> {
+    if account.owner != &crate::ID || account.data_is_empty() {
+        return Ok(None);
+    }
+    let data = account.try_borrow_data()?;
+    Ok(Some(IdentityHoldings::try_deserialize(&mut &data[..])?))
+}
+
+pub fn store_holdings(account: &AccountInfo, holdings: &IdentityHoldings) -> Result<()> {
+    let mut data = account.try_borrow_mut_data()?;
+    holdings.try_serialize(&mut &mut data[..])
+}
+
+// Adds `amount` to the holdings of an idendity if it has some, returns its new balance
+pub fn add_to_holdings(account: &AccountInfo, amount: u64) -> Result<Option<u64>> {
+    match load_holdings(account)? {
+        Some(mut holdings) => {
+            holdings.amount = holdings.amount.saturating_add(amount);
+            store_holdings(account, &holdings)?;
+            Ok(Some(holdings.amount))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn remove_from_holdings(account: &AccountInfo, amount: u64) -> Result<()> {
+    if let Some(mut holdings) = load_holdings(account)? {
+        holdings.amount = holdings.amount.saturating_sub(amount);
+        store_holdings(account, &holdings)?;
+    }
+    Ok(())
+}
+
+// Moves `amount` from the holdings of the sender to those of the receiver, returns the new holdings of the receiver if it has some
+pub fn move_holdings(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<Option<u64>> {
+    remove_from_holdings(from, amount)?;
+    add_to_holdings(to, amount)
+}
+
+// Instructions moving tokens outside of the transfer hook (seizures, recoveries) are passed the link of the token account
+// and the holdings of its subject unchecked: the token account may not be linked and the subject may have no holdings
+pub fn check_linked_holdings(link: &AccountInfo, holdings: &AccountInfo, mint: &Pubkey) -> Result<()> {
+    let matches = match load_link(link)? {
+        Some(link) => {
+            let (expected, _) =
+                Pubkey::find_program_address(&[b"holdings", mint.as_ref(), link.subject.as_ref()], &crate::ID);
+            expected == holdings.key()
+        }
+        None => load_holdings(holdings)?.is_none(),
+    };
+    if !matches {
+        return Err(BalanceCapError::WrongHoldings.into());
+    }
+    Ok(())
+}
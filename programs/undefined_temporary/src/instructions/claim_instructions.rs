@@ -0,0 +1,307 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::{expires_after, ConfigError, IdAccount, IdendityError, IssuerError, IssuerRecord, MintConfig, ProgramConfig, ProvenClaim, MAX_REQUIRED_CLAIMS};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClaimType {
+    // Space = 1
+    Country,     // ISO 3166-1 numeric code (840 for US)
+    KycLevel,    // Level of the KYC done by the issuer
+    Accredited,  // 0 or 1
+    AgeOver18,   // 0 or 1
+    EntityType,  // 0: individual, 1: company, 2: trust, 3: fund
+    RiskScore,   // Set by the issuer, the higher the riskier
+}
+
+impl ClaimType {
+    pub fn check_value(&self, value: u16) -> Result<()> {
+        let valid = match self {
+            ClaimType::Country => value <= 999,
+            ClaimType::Accredited | ClaimType::AgeOver18 => value <= 1,
+            ClaimType::EntityType => value <= 3,
+            ClaimType::KycLevel | ClaimType::RiskScore => true,
+        };
+        if !valid {
+            return Err(ClaimError::InvalidClaimValue.into());
+        }
+        Ok(())
+    }
+}
+
+// A claim is attached to the attestation of an issuer, it is removed with it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Claim {
+    // Total 53
+    pub claim_type: ClaimType, // 1
+    pub value: u16,            // 2
+    pub issuer: Pubkey,        // 32
+    pub version: u16,          // 2 - incremented each time the issuer updates the claim
+    pub issued_at: i64,        // 8
+    pub expires_at: i64,       // 8
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Comparison {
+    // Space = 1
+    Equal,
+    AtLeast,
+    AtMost,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClaimParty {
+    // Space = 1
+    Sender,
+    Receiver,
+    Both,
+}
+
+// e.g. "receiver must be accredited in US" is { Accredited, Equal, 1, Receiver } and { Country, Equal, 840, Receiver }
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimRequirement {
+    // Total 5
+    pub claim_type: ClaimType,  // 1
+    pub comparison: Comparison, // 1
+    pub value: u16,             // 2
+    pub party: ClaimParty,      // 1
+}
+
+impl ClaimRequirement {
+    pub fn applies_to(&self, party: ClaimParty) -> bool {
+        self.party == ClaimParty::Both || self.party == party
+    }
+
+    pub fn is_satisfied_by(&self, claim_type: ClaimType, value: u16) -> bool {
+        claim_type == self.claim_type
+            && match self.comparison {
+                Comparison::Equal => value == self.value,
+                Comparison::AtLeast => value >= self.value,
+                Comparison::AtMost => value <= self.value,
+            }
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(claim_type: ClaimType)]
+pub struct SetClaim<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey, constraint = issuer_record.active @ IssuerError::IssuerNotActive)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    // Root issuer of a sub-issuer
+    #[account(seeds = [b"issuer", issuer_record.parent.as_ref()], bump)]
+    pub parent_issuer_record: Option<Account<'info, IssuerRecord>>,
+    // Grows by one claim only when the issuer did not already set a claim of this type
+    #[account(mut, seeds = [b"identity", subject.key().as_ref()], bump, realloc = IdAccount::space(idendity.issuers.len(), idendity.recovered_token_address.len(), idendity.claims.len() + usize::from(idendity.find_claim(&issuer_record.id, claim_type).is_none())), realloc::payer = issuer, realloc::zero = false)]
+    pub idendity: Account<'info, IdAccount>,
+    /// CHECK: Token account of the idendity, or owner of a person idendity
+    pub subject: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveClaim<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    #[account(mut, seeds = [b"identity", subject.key().as_ref()], bump, realloc = IdAccount::space(idendity.issuers.len(), idendity.recovered_token_address.len(), idendity.claims.len().saturating_sub(1)), realloc::payer = issuer, realloc::zero = false)]
+    pub idendity: Account<'info, IdAccount>,
+    /// CHECK: Token account of the idendity, or owner of a person idendity
+    pub subject: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRequiredClaims<'info> {
+    #[account(seeds = [b"config"], bump, has_one = super_admin @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub super_admin: Signer<'info>,
+}
+
+#[event]
+pub struct ClaimSet {
+    pub subject: Pubkey,
+    pub issuer: Pubkey,
+    pub claim_type: ClaimType,
+    pub value: u16,
+    pub version: u16,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct ClaimRemoved {
+    pub subject: Pubkey,
+    pub issuer: Pubkey,
+    pub claim_type: ClaimType,
+}
+
+#[event]
+pub struct RequiredClaimsUpdated {
+    pub mint: Pubkey,
+    pub required_claims: Vec<ClaimRequirement>,
+}
+
+#[error_code]
+pub enum ClaimError {
+    #[msg("Invalid value for this claim type")]
+    InvalidClaimValue,
+    #[msg("Claim not found")]
+    ClaimNotFound,
+    #[msg("Too many required claims")]
+    TooManyRequiredClaims,
+    #[msg("The idendity does not have a claim required by the mint")]
+    MissingRequiredClaim,
+}
+
+pub fn _set_claim(ctx: Context<SetClaim>, claim_type: ClaimType, value: u16, validity_duration: i64) -> Result<()> {
+    claim_type.check_value(value)?;
+    let now = Clock::get()?.unix_timestamp;
+    let issuer_record = &ctx.accounts.issuer_record;
+    issuer_record.check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), now)?;
+    issuer_record.check_claim_in_scope(claim_type, value)?;
+    issuer_record.check_validity_duration(validity_duration)?;
+    let issuer_key = ctx.accounts.issuer_record.id;
+    let idendity = &mut ctx.accounts.idendity;
+
+    // Only an issuer that attested the idendity can attach claims to it
+    let issuer = idendity
+        .issuers
+        .iter()
+        .find(|issuer| issuer.key == issuer_key)
+        .ok_or(IdendityError::IssuerNotFound)?;
+    if issuer.revoked {
+        return Err(IdendityError::IdendityRevoked.into());
+    }
+
+    let expires_at = expires_after(now, validity_duration)?;
+    let version = match idendity.find_claim(&issuer_key, claim_type) {
+        Some(index) => {
+            let claim = &mut idendity.claims[index];
+            claim.value = value;
+            claim.version = claim.version.saturating_add(1);
+            claim.issued_at = now;
+            claim.expires_at = expires_at;
+            claim.version
+        }
+        None => {
+            idendity.claims.push(Claim {
+                claim_type,
+                value,
+                issuer: issuer_key,
+                version: 1,
+                issued_at: now,
+                expires_at,
+            });
+            1
+        }
+    };
+
+    emit!(ClaimSet {
+        subject: ctx.accounts.subject.key(),
+        issuer: issuer_key,
+        claim_type,
+        value,
+        version,
+        expires_at,
+    });
+    Ok(())
+}
+
+pub fn _remove_claim(ctx: Context<RemoveClaim>, claim_type: ClaimType) -> Result<()> {
+    let issuer_key = ctx.accounts.issuer_record.id;
+    let idendity = &mut ctx.accounts.idendity;
+    let index = idendity
+        .find_claim(&issuer_key, claim_type)
+        .ok_or(ClaimError::ClaimNotFound)?;
+    idendity.claims.remove(index);
+
+    emit!(ClaimRemoved {
+        subject: ctx.accounts.subject.key(),
+        issuer: issuer_key,
+        claim_type,
+    });
+    Ok(())
+}
+
+pub fn _set_required_claims(ctx: Context<SetRequiredClaims>, required_claims: Vec<ClaimRequirement>) -> Result<()> {
+    if required_claims.len() > MAX_REQUIRED_CLAIMS {
+        return Err(ClaimError::TooManyRequiredClaims.into());
+    }
+    for requirement in required_claims.iter() {
+        requirement.claim_type.check_value(requirement.value)?;
+    }
+    ctx.accounts.mint_config.required_claims = required_claims.clone();
+
+    emit!(RequiredClaimsUpdated {
+        mint: ctx.accounts.mint.key(),
+        required_claims,
+    });
+    Ok(())
+}
+
+// The claims that count for the idendity: not expired, their issuer still vouches for the idendity and its chain in the
+// issuer registry was checked (checked_issuers, see check_issuer_chain). The claims of an issuer revoked or suspended
+// in the registry, or of its sub-issuers, don't count.
+// The claims proven with a Merkle proof (see prove_claim) count as well as the claims stored in clear.
+pub fn valid_claims<'a>(
+    idendity: &'a IdAccount,
+    proven: &'a [ProvenClaim],
+    checked_issuers: &'a [Pubkey],
+    now: i64,
+) -> impl Iterator<Item = (ClaimType, u16)> + 'a {
+    let issuer_valid = move |key: &Pubkey| {
+        checked_issuers.contains(key)
+            && idendity
+                .issuers
+                .iter()
+                .any(|issuer| issuer.key == *key && issuer.check_valid(now).is_ok())
+    };
+    let stored = idendity
+        .claims
+        .iter()
+        .filter(move |claim| claim.expires_at >= now && issuer_valid(&claim.issuer))
+        .map(|claim| (claim.claim_type, claim.value));
+    let proven = proven
+        .iter()
+        .filter(move |claim| claim.expires_at >= now && issuer_valid(&claim.issuer))
+        .map(|claim| (claim.claim_type, claim.value));
+    stored.chain(proven)
+}
+
+// Values of all the valid claims of this type, an idendity can hold several (e.g. dual citizenship)
+pub fn valid_claim_values(
+    idendity: &IdAccount,
+    proven: &[ProvenClaim],
+    checked_issuers: &[Pubkey],
+    claim_type: ClaimType,
+    now: i64,
+) -> Vec<u16> {
+    valid_claims(idendity, proven, checked_issuers, now)
+        .filter(|(valid_type, _)| *valid_type == claim_type)
+        .map(|(_, value)| value)
+        .collect()
+}
+
+pub fn check_required_claims(
+    idendity: &IdAccount,
+    proven: &[ProvenClaim],
+    checked_issuers: &[Pubkey],
+    requirements: &[ClaimRequirement],
+    party: ClaimParty,
+    now: i64,
+) -> Result<()> {
+    for requirement in requirements.iter().filter(|r| r.applies_to(party)) {
+        let satisfied = valid_claims(idendity, proven, checked_issuers, now)
+            .any(|(claim_type, value)| requirement.is_satisfied_by(claim_type, value));
+        if !satisfied {
+            return Err(ClaimError::MissingRequiredClaim.into());
+        }
+    }
+    Ok(())
+}
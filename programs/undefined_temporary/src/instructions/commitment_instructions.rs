@@ -0,0 +1,208 @@
+use anchor_lang::{prelude::*, solana_program::hash::hashv};
+use anchor_spl::token_interface::TokenAccount;
+
+use crate::{check_linked, expires_after, ClaimType, IdAccount, IdentityLink, IdendityError, IssuerError, IssuerRecord, CLAIM_PROOF_VALIDITY, MAX_PROVEN_CLAIMS};
+
+// Merkle root over the claims of the idendity, the claims themselves stay off-chain.
+// A leaf is sha256(0x00 || claim_type || value (le) || salt), a node is sha256(0x01 || min(a, b) || max(a, b)).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimCommitment {
+    // Total 82
+    pub issuer: Pubkey,   // 32
+    pub root: [u8; 32],   // 32
+    pub version: u16,     // 2 - incremented each time the issuer updates the root
+    pub set_at: i64,      // 8
+    pub expires_at: i64,  // 8
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProvenClaim {
+    // Total 43
+    pub claim_type: ClaimType, // 1
+    pub value: u16,            // 2
+    pub issuer: Pubkey,        // 32
+    pub expires_at: i64,       // 8
+}
+
+// Claims disclosed by the owner with a Merkle proof, read by the transfer hook. A proof is only valid for CLAIM_PROOF_VALIDITY.
+#[account]
+pub struct ClaimProof {
+    pub token_account: Pubkey,     // 32
+    pub proven: Vec<ProvenClaim>,  // 4 + 43 * MAX_PROVEN_CLAIMS
+}
+
+#[derive(Accounts)]
+pub struct SetClaimCommitment<'info> {
+    pub issuer: Signer<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey, constraint = issuer_record.active @ IssuerError::IssuerNotActive)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    // Root issuer of a sub-issuer
+    #[account(seeds = [b"issuer", issuer_record.parent.as_ref()], bump)]
+    pub parent_issuer_record: Option<Account<'info, IssuerRecord>>,
+    #[account(mut, seeds = [b"identity", subject.key().as_ref()], bump)]
+    pub idendity: Account<'info, IdAccount>,
+    /// CHECK: Token account of the idendity, or owner of a person idendity
+    pub subject: UncheckedAccount<'info>,
+}
+
+// The owner proves the claim: a disclosure should not be made without its consent
+#[derive(Accounts)]
+pub struct ProveClaim<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"identity_link", token_account.key().as_ref()], bump)]
+    pub link: Account<'info, IdentityLink>,
+    #[account(seeds = [b"identity", link.subject.as_ref()], bump)]
+    pub idendity: Account<'info, IdAccount>,
+    #[account(token::authority = owner)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(init_if_needed, seeds = [b"claim_proof", token_account.key().as_ref()], bump, payer = owner, space = 8 + 32 + 4 + 43 * MAX_PROVEN_CLAIMS)]
+    pub claim_proof: Account<'info, ClaimProof>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+pub struct ClaimCommitmentSet {
+    pub subject: Pubkey,
+    pub issuer: Pubkey,
+    pub root: [u8; 32],
+    pub version: u16,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct ClaimProven {
+    pub token_account: Pubkey,
+    pub issuer: Pubkey,
+    pub claim_type: ClaimType,
+    pub expires_at: i64,
+}
+
+#[error_code]
+pub enum CommitmentError {
+    #[msg("The commitment of this idendity belongs to another issuer")]
+    CommitmentOwnedByAnotherIssuer,
+    #[msg("The idendity has no claim commitment")]
+    NoClaimCommitment,
+    #[msg("The claim commitment expired")]
+    ClaimCommitmentExpired,
+    #[msg("Invalid Merkle proof")]
+    InvalidClaimProof,
+}
+
+pub fn _set_claim_commitment(ctx: Context<SetClaimCommitment>, root: [u8; 32], validity_duration: i64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let issuer_record = &ctx.accounts.issuer_record;
+    issuer_record.check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), now)?;
+    issuer_record.check_validity_duration(validity_duration)?;
+    let issuer_key = ctx.accounts.issuer_record.id;
+    let idendity = &mut ctx.accounts.idendity;
+
+    let issuer = idendity
+        .issuers
+        .iter()
+        .find(|issuer| issuer.key == issuer_key)
+        .ok_or(IdendityError::IssuerNotFound)?;
+    if issuer.revoked {
+        return Err(IdendityError::IdendityRevoked.into());
+    }
+
+    let version = match &idendity.commitment {
+        Some(commitment) if commitment.issuer != issuer_key => {
+            return Err(CommitmentError::CommitmentOwnedByAnotherIssuer.into())
+        }
+        Some(commitment) => commitment.version.saturating_add(1),
+        None => 1,
+    };
+    let expires_at = expires_after(now, validity_duration)?;
+    idendity.commitment = Some(ClaimCommitment {
+        issuer: issuer_key,
+        root,
+        version,
+        set_at: now,
+        expires_at,
+    });
+
+    emit!(ClaimCommitmentSet {
+        subject: ctx.accounts.subject.key(),
+        issuer: issuer_key,
+        root,
+        version,
+        expires_at,
+    });
+    Ok(())
+}
+
+pub fn _prove_claim(
+    ctx: Context<ProveClaim>,
+    claim_type: ClaimType,
+    value: u16,
+    salt: [u8; 32],
+    proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let idendity = &ctx.accounts.idendity;
+    check_linked(&ctx.accounts.link, idendity, &ctx.accounts.token_account, &ctx.accounts.token_account.key())?;
+    let commitment = idendity
+        .commitment
+        .as_ref()
+        .ok_or(CommitmentError::NoClaimCommitment)?;
+    if commitment.expires_at < now {
+        return Err(CommitmentError::ClaimCommitmentExpired.into());
+    }
+    // The issuer of the commitment must still vouch for the idendity
+    idendity
+        .issuers
+        .iter()
+        .find(|issuer| issuer.key == commitment.issuer)
+        .ok_or(IdendityError::IssuerNotFound)?
+        .check_valid(now)?;
+
+    let leaf = hashv(&[&[0], &[claim_type as u8], &value.to_le_bytes(), &salt]).to_bytes();
+    if !verify_merkle_proof(leaf, &proof, commitment.root) {
+        return Err(CommitmentError::InvalidClaimProof.into());
+    }
+
+    let expires_at = commitment.expires_at.min(now + CLAIM_PROOF_VALIDITY);
+    let proven_claim = ProvenClaim {
+        claim_type,
+        value,
+        issuer: commitment.issuer,
+        expires_at,
+    };
+    let claim_proof = &mut ctx.accounts.claim_proof;
+    claim_proof.token_account = ctx.accounts.token_account.key();
+    // One proof per claim type: the expired ones and the previous proof of this type are replaced
+    claim_proof
+        .proven
+        .retain(|proven| proven.expires_at >= now && proven.claim_type != claim_type);
+    claim_proof.proven.push(proven_claim);
+
+    emit!(ClaimProven {
+        token_account: ctx.accounts.token_account.key(),
+        issuer: commitment.issuer,
+        claim_type,
+        expires_at,
+    });
+    Ok(())
+}
+
+fn verify_merkle_proof(leaf: [u8; 32], proof: &[[u8; 32]], root: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[&[1], &node, sibling]).to_bytes()
+        } else {
+            hashv(&[&[1], sibling, &node]).to_bytes()
+        }
+    });
+    computed == root
+}
+
+// The claim proof PDA of a token account may not exist, it then holds no proven claim
+pub fn load_proven_claims(account: &AccountInfo) -> Result<Vec<ProvenClaim>> {
+    if account.owner != &crate::ID || account.data_is_empty() {
+        return Ok(vec![]);
+    }
+    let data = account.try_borrow_data()?;
+    Ok(ClaimProof::try_deserialize(&mut &data[..])?.proven)
+}
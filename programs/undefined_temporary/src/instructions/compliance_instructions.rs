@@ -0,0 +1,343 @@
+use anchor_lang::{prelude::*, solana_program::program};
+use anchor_spl::{
+    token_2022::spl_token_2022,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{check_linked_holdings, freeze_with_mint, move_holdings, thaw_with_mint, ConfigError, MintConfig, ProgramConfig, Role, MAX_CASE_REFERENCE_LENGTH};
+
+// Only the accounts with the Compliance role can freeze, thaw and seize token accounts (executing regulators' orders)
+
+// One FreezeRecord per token account, it keeps the last freeze/thaw action and why it was taken.
+// The full history can be rebuilt from the emitted events.
+#[account]
+pub struct FreezeRecord {
+    pub token_account: Pubkey,  // 32
+    pub frozen: bool,           // 1
+    pub reason_code: u16,       // 2
+    pub case_reference: String, // 4 + MAX_CASE_REFERENCE_LENGTH
+    pub authority: Pubkey,      // 32
+    pub timestamp: i64,         // 8
+}
+
+// A SeizureRecord is created for each forced transfer, there can be several per token account (one per case)
+#[account]
+pub struct SeizureRecord {
+    pub case_id: u64,        // 8
+    pub source: Pubkey,      // 32
+    pub destination: Pubkey, // 32
+    pub amount: u64,         // 8
+    pub timestamp: i64,      // 8
+    pub authority: Pubkey,   // 32
+}
+
+#[derive(Accounts)]
+pub struct FreezeTokenAccount<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &authority.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(init_if_needed, seeds = [b"freeze_record", token_account.key().as_ref()], bump, payer = authority, space = 8 + 32 + 1 + 2 + 4 + MAX_CASE_REFERENCE_LENGTH + 32 + 8)]
+    pub freeze_record: Account<'info, FreezeRecord>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ThawTokenAccount<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &authority.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(init_if_needed, seeds = [b"freeze_record", token_account.key().as_ref()], bump, payer = authority, space = 8 + 32 + 1 + 2 + 4 + MAX_CASE_REFERENCE_LENGTH + 32 + 8)]
+    pub freeze_record: Account<'info, FreezeRecord>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, case_id: u64)]
+pub struct ForcedTransfer<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &authority.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(init, seeds = [b"seizure_record", source_token.key().as_ref(), case_id.to_le_bytes().as_ref()], bump, payer = authority, space = 8 + 8 + 32 + 32 + 8 + 8 + 32)]
+    pub seizure_record: Account<'info, SeizureRecord>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = mint, constraint = destination_token.key() != source_token.key() @ ComplianceError::SameSourceAndDestination)]
+    pub destination_token: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    /// CHECK: Link of the source token account, may not exist (see check_linked_holdings)
+    #[account(seeds = [b"identity_link", source_token.key().as_ref()], bump)]
+    pub source_link: UncheckedAccount<'info>,
+    /// CHECK: Holdings of the subject of source_link, may not exist
+    #[account(mut)]
+    pub source_holdings: UncheckedAccount<'info>,
+    /// CHECK: Link of the destination token account, may not exist (see check_linked_holdings)
+    #[account(seeds = [b"identity_link", destination_token.key().as_ref()], bump)]
+    pub destination_link: UncheckedAccount<'info>,
+    /// CHECK: Holdings of the subject of destination_link, may not exist
+    #[account(mut)]
+    pub destination_holdings: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+pub struct TokenAccountFrozen {
+    pub token_account: Pubkey,
+    pub authority: Pubkey,
+    pub reason_code: u16,
+    pub case_reference: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenAccountThawed {
+    pub token_account: Pubkey,
+    pub authority: Pubkey,
+    pub reason_code: u16,
+    pub case_reference: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokensSeized {
+    pub case_id: u64,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[error_code]
+pub enum ComplianceError {
+    #[msg("Case reference too long")]
+    CaseReferenceTooLong,
+    #[msg("Token account already frozen by Compliance")]
+    AlreadyFrozen,
+    #[msg("Token account not frozen")]
+    NotFrozen,
+    #[msg("Source and destination token accounts must be different")]
+    SameSourceAndDestination,
+    #[msg("Insufficient funds to seize")]
+    InsufficientFunds,
+}
+
+pub fn _freeze_account(
+    ctx: Context<FreezeTokenAccount>,
+    reason_code: u16,
+    case_reference: String,
+) -> Result<()> {
+    if case_reference.len() > MAX_CASE_REFERENCE_LENGTH {
+        return Err(ComplianceError::CaseReferenceTooLong.into());
+    }
+    if ctx.accounts.freeze_record.frozen {
+        return Err(ComplianceError::AlreadyFrozen.into());
+    }
+
+    // A token account frozen by default (not verified yet) is already frozen: the freeze is only recorded,
+    // which keeps the issuance and link flows from thawing it
+    if !ctx.accounts.token_account.is_frozen() {
+        freeze_with_mint(
+            &ctx.accounts.token_account.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.bumps.mint,
+        )?;
+    }
+
+    let timestamp = Clock::get()?.unix_timestamp;
+    let freeze_record = &mut ctx.accounts.freeze_record;
+    freeze_record.token_account = ctx.accounts.token_account.key();
+    freeze_record.frozen = true;
+    freeze_record.reason_code = reason_code;
+    freeze_record.case_reference = case_reference.clone();
+    freeze_record.authority = ctx.accounts.authority.key();
+    freeze_record.timestamp = timestamp;
+
+    emit!(TokenAccountFrozen {
+        token_account: ctx.accounts.token_account.key(),
+        authority: ctx.accounts.authority.key(),
+        reason_code,
+        case_reference,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+pub fn _thaw_account(
+    ctx: Context<ThawTokenAccount>,
+    reason_code: u16,
+    case_reference: String,
+) -> Result<()> {
+    if case_reference.len() > MAX_CASE_REFERENCE_LENGTH {
+        return Err(ComplianceError::CaseReferenceTooLong.into());
+    }
+    if !ctx.accounts.token_account.is_frozen() {
+        return Err(ComplianceError::NotFrozen.into());
+    }
+
+    thaw_with_mint(
+        &ctx.accounts.token_account.to_account_info(),
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        ctx.bumps.mint,
+    )?;
+
+    let timestamp = Clock::get()?.unix_timestamp;
+    let freeze_record = &mut ctx.accounts.freeze_record;
+    freeze_record.token_account = ctx.accounts.token_account.key();
+    freeze_record.frozen = false;
+    freeze_record.reason_code = reason_code;
+    freeze_record.case_reference = case_reference.clone();
+    freeze_record.authority = ctx.accounts.authority.key();
+    freeze_record.timestamp = timestamp;
+
+    emit!(TokenAccountThawed {
+        token_account: ctx.accounts.token_account.key(),
+        authority: ctx.accounts.authority.key(),
+        reason_code,
+        case_reference,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+// A token account frozen by the Compliance role stays frozen until Compliance thaws it:
+// the issuance and link flows only thaw the accounts frozen by default (DefaultAccountState)
+pub fn frozen_by_compliance(freeze_record: &AccountInfo) -> Result<bool> {
+    if freeze_record.owner != &crate::ID || freeze_record.data_is_empty() {
+        return Ok(false);
+    }
+    let data = freeze_record.try_borrow_data()?;
+    Ok(FreezeRecord::try_deserialize(&mut &data[..])?.frozen)
+}
+
+// The forced transfer is a burn followed by a mint, both signed by the mint as permanent delegate.
+// A transfer_checked would make Token-2022 call back this program for the transfer hook, which the runtime
+// rejects (reentrancy): the transfer hook (and its two auth checks) is therefore never involved in a seizure.
+pub fn _forced_transfer(ctx: Context<ForcedTransfer>, amount: u64, case_id: u64) -> Result<()> {
+    if ctx.accounts.source_token.amount < amount {
+        return Err(ComplianceError::InsufficientFunds.into());
+    }
+
+    let seeds: &[&[&[u8]]] = &[&[b"mint", &[ctx.bumps.mint]]];
+
+    // A frozen account cannot be burnt from: it is thawed for the time of the seizure
+    let source_frozen = ctx.accounts.source_token.is_frozen();
+    if source_frozen {
+        thaw_with_mint(
+            &ctx.accounts.source_token.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.bumps.mint,
+        )?;
+    }
+    seizure_burn(&ctx, seeds, amount)?;
+    seizure_mint(&ctx, seeds, amount)?;
+    if source_frozen {
+        freeze_with_mint(
+            &ctx.accounts.source_token.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.bumps.mint,
+        )?;
+    }
+
+    let source_balance = ctx.accounts.source_token.amount;
+    let destination_balance = ctx.accounts.destination_token.amount;
+    let source = ctx.accounts.source_token.key();
+    let destination = ctx.accounts.destination_token.key();
+    let holder_count = &mut ctx.accounts.mint_config.holder_count;
+    holder_count.record(&source, source_balance, source_balance - amount)?;
+    holder_count.record(&destination, destination_balance, destination_balance.saturating_add(amount))?;
+
+    // The seized tokens follow the idendities, the balance cap of the destination does not apply to a seizure
+    let mint = ctx.accounts.mint.key();
+    check_linked_holdings(&ctx.accounts.source_link, &ctx.accounts.source_holdings, &mint)?;
+    check_linked_holdings(&ctx.accounts.destination_link, &ctx.accounts.destination_holdings, &mint)?;
+    move_holdings(&ctx.accounts.source_holdings, &ctx.accounts.destination_holdings, amount)?;
+
+    let timestamp = Clock::get()?.unix_timestamp;
+    let seizure_record = &mut ctx.accounts.seizure_record;
+    seizure_record.case_id = case_id;
+    seizure_record.source = ctx.accounts.source_token.key();
+    seizure_record.destination = ctx.accounts.destination_token.key();
+    seizure_record.amount = amount;
+    seizure_record.timestamp = timestamp;
+    seizure_record.authority = ctx.accounts.authority.key();
+
+    emit!(TokensSeized {
+        case_id,
+        source: ctx.accounts.source_token.key(),
+        destination: ctx.accounts.destination_token.key(),
+        amount,
+        authority: ctx.accounts.authority.key(),
+        timestamp,
+    });
+
+    Ok(())
+}
+
+#[inline(always)]
+pub fn seizure_burn(ctx: &Context<ForcedTransfer>, seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+    let ix = spl_token_2022::instruction::burn(
+        &spl_token_2022::id(),
+        &ctx.accounts.source_token.key(),
+        &ctx.accounts.mint.key(),
+        &ctx.accounts.mint.key(),
+        &[],
+        amount,
+    )?;
+
+    program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.source_token.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        ],
+        seeds,
+    )?;
+    Ok(())
+}
+
+#[inline(always)]
+pub fn seizure_mint(ctx: &Context<ForcedTransfer>, seeds: &[&[&[u8]]], amount: u64) -> Result<()> {
+    let ix = spl_token_2022::instruction::mint_to(
+        &spl_token_2022::id(),
+        &ctx.accounts.mint.key(),
+        &ctx.accounts.destination_token.key(),
+        &ctx.accounts.mint.key(),
+        &[],
+        amount,
+    )?;
+
+    program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.destination_token.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+        ],
+        seeds,
+    )?;
+    Ok(())
+}
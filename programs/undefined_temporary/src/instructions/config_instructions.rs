@@ -0,0 +1,174 @@
+use anchor_lang::prelude::*;
+
+use crate::{program::UndefinedTemporary, MAX_ROLE_ASSIGNMENTS};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    // Space = 1
+    MinterAdmin, // Mints tokens
+    IssuerAdmin, // Registers and revokes idendity issuers
+    Compliance,  // Freezes, thaws and seizes token accounts
+    Pauser,      // Pauses and unpauses transfers
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RoleAssignment {
+    // Total 33
+    pub role: Role,      // 1
+    pub account: Pubkey, // 32
+}
+
+// Program-wide configuration: the super admin manages the roles, every privileged instruction checks here the role it needs.
+// The super admin handover is done in two steps (propose/accept) so that it can't be given to a wrong address.
+#[account]
+pub struct ProgramConfig {
+    pub super_admin: Pubkey,                 // 32
+    pub pending_super_admin: Option<Pubkey>, // 1 + 32
+    pub roles: Vec<RoleAssignment>,          // 4 + 33 * MAX_ROLE_ASSIGNMENTS
+    pub paused: bool,                        // 1 - Global pause, for all mints
+}
+
+impl ProgramConfig {
+    pub fn has_role(&self, role: Role, account: &Pubkey) -> bool {
+        self.roles
+            .iter()
+            .any(|assignment| assignment.role == role && assignment.account == *account)
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(init, seeds = [b"config"], bump, payer = authority, space = 8 + 32 + 33 + 4 + 33 * MAX_ROLE_ASSIGNMENTS + 1)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, UndefinedTemporary>,
+    // Only the upgrade authority of the program can initialize the config (avoids front-running the initialization)
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ConfigError::NotUpgradeAuthority)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRole<'info> {
+    #[account(mut, seeds = [b"config"], bump, has_one = super_admin @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    pub super_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeSuperAdmin<'info> {
+    #[account(mut, seeds = [b"config"], bump, has_one = super_admin @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    pub super_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptSuperAdmin<'info> {
+    #[account(mut, seeds = [b"config"], bump, constraint = config.pending_super_admin == Some(new_super_admin.key()) @ ConfigError::NotPendingSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    pub new_super_admin: Signer<'info>,
+}
+
+#[event]
+pub struct RoleGranted {
+    pub role: Role,
+    pub account: Pubkey,
+}
+
+#[event]
+pub struct RoleRevoked {
+    pub role: Role,
+    pub account: Pubkey,
+}
+
+#[event]
+pub struct SuperAdminProposed {
+    pub super_admin: Pubkey,
+    pub pending_super_admin: Pubkey,
+}
+
+#[event]
+pub struct SuperAdminAccepted {
+    pub previous_super_admin: Pubkey,
+    pub super_admin: Pubkey,
+}
+
+#[error_code]
+pub enum ConfigError {
+    #[msg("Only the upgrade authority of the program can initialize the config")]
+    NotUpgradeAuthority,
+    #[msg("Not the super admin")]
+    NotSuperAdmin,
+    #[msg("Not the pending super admin")]
+    NotPendingSuperAdmin,
+    #[msg("The signer does not have the required role")]
+    MissingRole,
+    #[msg("Role already granted")]
+    RoleAlreadyGranted,
+    #[msg("Role not granted")]
+    RoleNotGranted,
+    #[msg("Too many role assignments")]
+    TooManyRoles,
+}
+
+pub fn _initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.super_admin = ctx.accounts.authority.key();
+    config.pending_super_admin = None;
+    config.roles = vec![];
+    config.paused = false;
+    Ok(())
+}
+
+pub fn _grant_role(ctx: Context<UpdateRole>, role: Role, account: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    if config.has_role(role, &account) {
+        return Err(ConfigError::RoleAlreadyGranted.into());
+    }
+    if config.roles.len() >= MAX_ROLE_ASSIGNMENTS {
+        return Err(ConfigError::TooManyRoles.into());
+    }
+    config.roles.push(RoleAssignment { role, account });
+
+    emit!(RoleGranted { role, account });
+    Ok(())
+}
+
+pub fn _revoke_role(ctx: Context<UpdateRole>, role: Role, account: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    if !config.has_role(role, &account) {
+        return Err(ConfigError::RoleNotGranted.into());
+    }
+    config
+        .roles
+        .retain(|assignment| !(assignment.role == role && assignment.account == account));
+
+    emit!(RoleRevoked { role, account });
+    Ok(())
+}
+
+pub fn _propose_super_admin(ctx: Context<ProposeSuperAdmin>, new_super_admin: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.pending_super_admin = Some(new_super_admin);
+
+    emit!(SuperAdminProposed {
+        super_admin: config.super_admin,
+        pending_super_admin: new_super_admin,
+    });
+    Ok(())
+}
+
+pub fn _accept_super_admin(ctx: Context<AcceptSuperAdmin>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    let previous_super_admin = config.super_admin;
+    config.super_admin = ctx.accounts.new_super_admin.key();
+    config.pending_super_admin = None;
+
+    emit!(SuperAdminAccepted {
+        previous_super_admin,
+        super_admin: config.super_admin,
+    });
+    Ok(())
+}
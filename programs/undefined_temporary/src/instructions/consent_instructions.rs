@@ -0,0 +1,118 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::{ConfigError, MintConfig, ProgramConfig};
+
+// Pre-authorization given by a token account owner to a specific issuer, consumed when the issuer attests the idendity
+#[account]
+pub struct IssuerConsent {
+    pub owner: Pubkey,         // 32
+    pub token_account: Pubkey, // 32
+    pub issuer: Pubkey,        // 32 - issuer id (see IssuerRecord)
+    pub created_at: i64,       // 8
+}
+
+#[derive(Accounts)]
+#[instruction(issuer: Pubkey)]
+pub struct GrantIssuerConsent<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(token::authority = owner)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(init, seeds = [b"consent", token_account.key().as_ref(), issuer.as_ref()], bump, payer = owner, space = 8 + 32 + 32 + 32 + 8)]
+    pub consent: Account<'info, IssuerConsent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeIssuerConsent<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"consent", consent.token_account.as_ref(), consent.issuer.as_ref()], bump, has_one = owner, close = owner)]
+    pub consent: Account<'info, IssuerConsent>,
+}
+
+#[derive(Accounts)]
+pub struct SetOwnerConsentRequired<'info> {
+    #[account(seeds = [b"config"], bump, has_one = super_admin @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub super_admin: Signer<'info>,
+}
+
+#[event]
+pub struct IssuerConsentGranted {
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+    pub issuer: Pubkey,
+}
+
+#[event]
+pub struct IssuerConsentRevoked {
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+    pub issuer: Pubkey,
+}
+
+#[event]
+pub struct OwnerConsentRequiredUpdated {
+    pub mint: Pubkey,
+    pub owner_consent_required: bool,
+}
+
+#[error_code]
+pub enum ConsentError {
+    #[msg("The token account owner has to sign or consent to the issuer")]
+    OwnerConsentRequired,
+}
+
+pub fn _grant_issuer_consent(ctx: Context<GrantIssuerConsent>, issuer: Pubkey) -> Result<()> {
+    let consent = &mut ctx.accounts.consent;
+    consent.owner = ctx.accounts.owner.key();
+    consent.token_account = ctx.accounts.token_account.key();
+    consent.issuer = issuer;
+    consent.created_at = Clock::get()?.unix_timestamp;
+
+    emit!(IssuerConsentGranted {
+        owner: consent.owner,
+        token_account: consent.token_account,
+        issuer,
+    });
+    Ok(())
+}
+
+pub fn _revoke_issuer_consent(ctx: Context<RevokeIssuerConsent>) -> Result<()> {
+    let consent = &ctx.accounts.consent;
+
+    emit!(IssuerConsentRevoked {
+        owner: consent.owner,
+        token_account: consent.token_account,
+        issuer: consent.issuer,
+    });
+    Ok(())
+}
+
+pub fn _set_owner_consent_required(ctx: Context<SetOwnerConsentRequired>, required: bool) -> Result<()> {
+    ctx.accounts.mint_config.owner_consent_required = required;
+
+    emit!(OwnerConsentRequiredUpdated {
+        mint: ctx.accounts.mint.key(),
+        owner_consent_required: required,
+    });
+    Ok(())
+}
+
+// When the mint requires it, the owner either co-signs the attestation or has consented to this issuer beforehand
+pub fn check_owner_consent(
+    mint_config: &MintConfig,
+    owner: &AccountInfo,
+    consent: &Option<Account<IssuerConsent>>,
+) -> Result<()> {
+    if !mint_config.owner_consent_required || owner.is_signer || consent.is_some() {
+        return Ok(());
+    }
+    Err(ConsentError::OwnerConsentRequired.into())
+}
@@ -0,0 +1,272 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{create_account, CreateAccount},
+    Discriminator,
+};
+
+use crate::{ConfigError, ProgramConfig, Role, MAX_DENY_LIST_IMPORT, MAX_LIST_SOURCE_LENGTH};
+
+// Denied addresses can't send nor receive tokens, whatever the status of their idendity.
+// An entry is keyed by a token account or by an owner (which denies all its token accounts).
+#[account]
+pub struct DenyListEntry {
+    pub address: Pubkey,     // 32
+    pub reason_code: u16,    // 2
+    pub list_source: String, // 4 + MAX_LIST_SOURCE_LENGTH - e.g. the sanctions list the address comes from
+    pub authority: Pubkey,   // 32
+    pub timestamp: i64,      // 8
+}
+
+impl DenyListEntry {
+    pub const SPACE: usize = 8 + 32 + 2 + 4 + MAX_LIST_SOURCE_LENGTH + 32 + 8;
+}
+
+// Addresses of the deny list entries, kept sorted and without duplicates as entries are added, imported and removed.
+// The transfer hook looks the sender and the receiver up in this single account.
+#[account]
+pub struct DenyList {
+    pub addresses: Vec<Pubkey>, // 4 + 32 * len
+}
+
+impl DenyList {
+    pub fn space(len: usize) -> usize {
+        8 + 4 + 32 * len
+    }
+
+    pub fn insert(&mut self, address: Pubkey) {
+        if let Err(index) = self.addresses.binary_search(&address) {
+            self.addresses.insert(index, address);
+        }
+    }
+
+    pub fn remove(&mut self, address: &Pubkey) {
+        if let Ok(index) = self.addresses.binary_search(address) {
+            self.addresses.remove(index);
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct DeniedAddress {
+    pub address: Pubkey,
+    pub reason_code: u16,
+}
+
+#[derive(Accounts)]
+pub struct InitializeDenyList<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &authority.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(init, seeds = [b"deny_list"], bump, payer = authority, space = DenyList::space(0))]
+    pub deny_list: Account<'info, DenyList>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct AddToDenyList<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &authority.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(init_if_needed, seeds = [b"deny_list", address.as_ref()], bump, payer = authority, space = DenyListEntry::SPACE)]
+    pub deny_list_entry: Account<'info, DenyListEntry>,
+    #[account(mut, seeds = [b"deny_list"], bump, realloc = DenyList::space(deny_list.addresses.len() + 1), realloc::payer = authority, realloc::zero = false)]
+    pub deny_list: Account<'info, DenyList>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct RemoveFromDenyList<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &authority.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"deny_list", address.as_ref()], bump, close = authority)]
+    pub deny_list_entry: Account<'info, DenyListEntry>,
+    // Keeps its size, the next address added takes the free slot
+    #[account(mut, seeds = [b"deny_list"], bump)]
+    pub deny_list: Account<'info, DenyList>,
+}
+
+// The entries are passed as remaining accounts, in the order of the imported addresses
+#[derive(Accounts)]
+#[instruction(addresses: Vec<DeniedAddress>)]
+pub struct ImportDenyList<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &authority.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"deny_list"], bump, realloc = DenyList::space(deny_list.addresses.len() + addresses.len()), realloc::payer = authority, realloc::zero = false)]
+    pub deny_list: Account<'info, DenyList>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+pub struct AddressDenied {
+    pub address: Pubkey,
+    pub reason_code: u16,
+    pub list_source: String,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AddressAllowed {
+    pub address: Pubkey,
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[error_code]
+pub enum DenyListError {
+    #[msg("The sender or the receiver is on the deny list")]
+    AddressDenied,
+    #[msg("List source too long")]
+    ListSourceTooLong,
+    #[msg("Too many addresses in one import")]
+    ImportTooLarge,
+    #[msg("One deny list entry must be passed per imported address")]
+    ImportAccountsMismatch,
+    #[msg("The deny list entry does not match the imported address")]
+    WrongDenyListEntry,
+}
+
+pub fn _add_to_deny_list(
+    ctx: Context<AddToDenyList>,
+    address: Pubkey,
+    reason_code: u16,
+    list_source: String,
+) -> Result<()> {
+    if list_source.len() > MAX_LIST_SOURCE_LENGTH {
+        return Err(DenyListError::ListSourceTooLong.into());
+    }
+    let now = Clock::get()?.unix_timestamp;
+
+    let entry = &mut ctx.accounts.deny_list_entry;
+    entry.address = address;
+    entry.reason_code = reason_code;
+    entry.list_source = list_source.clone();
+    entry.authority = ctx.accounts.authority.key();
+    entry.timestamp = now;
+    ctx.accounts.deny_list.insert(address);
+
+    emit!(AddressDenied {
+        address,
+        reason_code,
+        list_source,
+        authority: ctx.accounts.authority.key(),
+        timestamp: now,
+    });
+    Ok(())
+}
+
+pub fn _initialize_deny_list(_ctx: Context<InitializeDenyList>) -> Result<()> {
+    Ok(())
+}
+
+pub fn _remove_from_deny_list(ctx: Context<RemoveFromDenyList>, address: Pubkey) -> Result<()> {
+    ctx.accounts.deny_list.remove(&address);
+
+    emit!(AddressAllowed {
+        address,
+        authority: ctx.accounts.authority.key(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+// Creates or updates one entry per address, all from the same list source
+pub fn _import_deny_list<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ImportDenyList<'info>>,
+    addresses: Vec<DeniedAddress>,
+    list_source: String,
+) -> Result<()> {
+    if list_source.len() > MAX_LIST_SOURCE_LENGTH {
+        return Err(DenyListError::ListSourceTooLong.into());
+    }
+    if addresses.len() > MAX_DENY_LIST_IMPORT {
+        return Err(DenyListError::ImportTooLarge.into());
+    }
+    if addresses.len() != ctx.remaining_accounts.len() {
+        return Err(DenyListError::ImportAccountsMismatch.into());
+    }
+    let now = Clock::get()?.unix_timestamp;
+    let authority = ctx.accounts.authority.key();
+
+    for (denied, entry_info) in addresses.iter().zip(ctx.remaining_accounts.iter()) {
+        let (expected, bump) =
+            Pubkey::find_program_address(&[b"deny_list", denied.address.as_ref()], ctx.program_id);
+        if expected != entry_info.key() {
+            return Err(DenyListError::WrongDenyListEntry.into());
+        }
+
+        if entry_info.data_is_empty() {
+            let lamports = Rent::get()?.minimum_balance(DenyListEntry::SPACE);
+            create_account(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    CreateAccount {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: entry_info.clone(),
+                    },
+                )
+                .with_signer(&[&[b"deny_list", denied.address.as_ref(), &[bump]]]),
+                lamports,
+                DenyListEntry::SPACE as u64,
+                ctx.program_id,
+            )?;
+        } else if entry_info.owner != ctx.program_id {
+            return Err(DenyListError::WrongDenyListEntry.into());
+        }
+
+        let entry = DenyListEntry {
+            address: denied.address,
+            reason_code: denied.reason_code,
+            list_source: list_source.clone(),
+            authority,
+            timestamp: now,
+        };
+        entry.try_serialize(&mut &mut entry_info.try_borrow_mut_data()?[..])?;
+        ctx.accounts.deny_list.insert(denied.address);
+
+        emit!(AddressDenied {
+            address: denied.address,
+            reason_code: denied.reason_code,
+            list_source: list_source.clone(),
+            authority,
+            timestamp: now,
+        });
+    }
+    Ok(())
+}
+
+// The transfer hook searches the sorted addresses in place, the list is not deserialized
+pub fn check_not_denied(deny_list: &AccountInfo, addresses: &[Pubkey]) -> Result<()> {
+    let data = deny_list.try_borrow_data()?;
+    if data.len() < 12 || data[..8] != DenyList::DISCRIMINATOR {
+        return Err(ErrorCode::AccountDidNotDeserialize.into());
+    }
+    let len = u32::from_le_bytes([data[8], data[9], data[10], data[11]]) as usize;
+    let denied = data
+        .get(12..12 + 32 * len)
+        .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+    let is_denied = |address: &Pubkey| {
+        let (mut low, mut high) = (0, len);
+        while low < high {
+            let middle = (low + high) / 2;
+            match denied[32 * middle..32 * middle + 32].cmp(address.as_ref()) {
+                std::cmp::Ordering::Equal => return true,
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+            }
+        }
+        false
+    };
+    if addresses.iter().any(is_denied) {
+        return Err(DenyListError::AddressDenied.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::{ConfigError, MintConfig, ProgramConfig};
+
+// Recount of the holders in progress, the token accounts are counted in the order of their addresses
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct HolderRecount {
+    pub counted: u64,   // 8 - holders among the token accounts up to the cursor
+    pub cursor: Pubkey, // 32 - last token account counted
+}
+
+// Number of token accounts of the mint holding tokens, kept in MintConfig and followed by the transfer hook, mint_to,
+// recoveries and seizures. Private placements can limit it: a transfer or a mint making a new holder fails once the maximum is reached.
+// Burns by the owner of a token account don't go through the transfer hook and leave the count too high,
+// the super admin reconciles it with a recount (see start_holder_recount).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct HolderCount {
+    pub holders: u64,                     // 8
+    pub max_holders: u64,                 // 8 - 0: no limit
+    pub recount: Option<HolderRecount>,   // 1 + 40
+}
+
+impl HolderCount {
+    pub const SPACE: usize = 8 + 8 + 1 + 8 + 32;
+
+    // Records the change of the balance of a token account, also in the recount if the token account was already counted
+    pub fn record(&mut self, token_account: &Pubkey, balance_before: u64, balance_after: u64) -> Result<()> {
+        let counted = match self.recount.as_mut() {
+            Some(recount) if *token_account <= recount.cursor => Some(&mut recount.counted),
+            _ => None,
+        };
+        if balance_before == 0 && balance_after > 0 {
+            if let Some(counted) = counted {
+                *counted = counted.saturating_add(1);
+            }
+            self.holders = self.holders.saturating_add(1);
+            if self.max_holders > 0 && self.holders > self.max_holders {
+                return Err(HolderCountError::MaxHoldersReached.into());
+            }
+        } else if balance_before > 0 && balance_after == 0 {
+            if let Some(counted) = counted {
+                *counted = counted.saturating_sub(1);
+            }
+            self.holders = self.holders.saturating_sub(1);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetMaxHolders<'info> {
+    #[account(seeds = [b"config"], bump, has_one = super_admin @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub super_admin: Signer<'info>,
+}
+
+// The same accounts start, continue and finish a recount
+#[derive(Accounts)]
+pub struct RecountHolders<'info> {
+    #[account(seeds = [b"config"], bump, has_one = super_admin @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub super_admin: Signer<'info>,
+}
+
+#[event]
+pub struct MaxHoldersUpdated {
+    pub mint: Pubkey,
+    pub max_holders: u64,
+    pub holders: u64,
+}
+
+#[event]
+pub struct HolderCountReconciled {
+    pub mint: Pubkey,
+    pub previous_holders: u64,
+    pub holders: u64,
+}
+
+#[error_code]
+pub enum HolderCountError {
+    #[msg("The maximum number of holders is reached")]
+    MaxHoldersReached,
+    #[msg("No recount of the holders is in progress")]
+    NoRecountInProgress,
+    #[msg("The token accounts must be of the mint and sorted by address after the last counted one")]
+    InvalidRecountAccount,
+}
+
+// The maximum can be set below the current number of holders: no new holder is accepted until enough holders leave
+pub fn _set_max_holders(ctx: Context<SetMaxHolders>, max_holders: u64) -> Result<()> {
+    let holder_count = &mut ctx.accounts.mint_config.holder_count;
+    holder_count.max_holders = max_holders;
+
+    emit!(MaxHoldersUpdated {
+        mint: ctx.accounts.mint.key(),
+        max_holders,
+        holders: holder_count.holders,
+    });
+    Ok(())
+}
+
+// Starts over a recount in progress
+pub fn _start_holder_recount(ctx: Context<RecountHolders>) -> Result<()> {
+    ctx.accounts.mint_config.holder_count.recount = Some(HolderRecount {
+        counted: 0,
+        cursor: Pubkey::default(),
+    });
+    Ok(())
+}
+
+// Every token account of the mint is passed once as a remaining account, in the order of the addresses, over as many
+// transactions as needed. A token account is only counted once: its address must follow the last counted one.
+pub fn _recount_holders<'info>(ctx: Context<'_, '_, 'info, 'info, RecountHolders<'info>>) -> Result<()> {
+    let mint = ctx.accounts.mint.key();
+    let recount = ctx
+        .accounts
+        .mint_config
+        .holder_count
+        .recount
+        .as_mut()
+        .ok_or(HolderCountError::NoRecountInProgress)?;
+    for account in ctx.remaining_accounts.iter() {
+        if account.key() <= recount.cursor {
+            return Err(HolderCountError::InvalidRecountAccount.into());
+        }
+        let token_account = InterfaceAccount::<TokenAccount>::try_from(account)?;
+        if token_account.mint != mint {
+            return Err(HolderCountError::InvalidRecountAccount.into());
+        }
+        if token_account.amount > 0 {
+            recount.counted = recount.counted.saturating_add(1);
+        }
+        recount.cursor = account.key();
+    }
+    Ok(())
+}
+
+// The super admin is trusted to have passed all the token accounts of the mint
+pub fn _finish_holder_recount(ctx: Context<RecountHolders>) -> Result<()> {
+    let holder_count = &mut ctx.accounts.mint_config.holder_count;
+    let recount = holder_count.recount.take().ok_or(HolderCountError::NoRecountInProgress)?;
+    let previous_holders = holder_count.holders;
+    holder_count.holders = recount.counted;
+
+    emit!(HolderCountReconciled {
+        mint: ctx.accounts.mint.key(),
+        previous_holders,
+        holders: recount.counted,
+    });
+    Ok(())
+}
@@ -1,16 +1,39 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{check_owner_consent, expires_after, frozen_by_compliance, set_link, thaw_with_mint, Claim, IdentityLink, ClaimCommitment, ClaimType, ConfigError, IssuerConsent, IssuerError, IssuerRecord, MintConfig, ProgramConfig, ValidityError, EXPIRED_REASON_CODE};
 
 #[derive(Accounts)]
 pub struct InitializeId<'info> {
     #[account(mut)]
     pub issuer: Signer<'info>,
-    #[account(init, seeds = [b"identity", token_account.key().as_ref()], bump, payer = issuer, space = 8 + 32 + 32 + 4 + 49 + 4)]
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey, constraint = issuer_record.active @ IssuerError::IssuerNotActive)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    // Root issuer of a sub-issuer
+    #[account(seeds = [b"issuer", issuer_record.parent.as_ref()], bump)]
+    pub parent_issuer_record: Option<Account<'info, IssuerRecord>>,
+    #[account(init, seeds = [b"identity", token_account.key().as_ref()], bump, payer = issuer, space = IdAccount::space(1, 0, 0))]
     pub idendity: Account<'info, IdAccount>,
-    /// CHECK:
+    // The token account is linked to its own idendity, replacing a link to a person idendity
+    #[account(init_if_needed, seeds = [b"identity_link", token_account.key().as_ref()], bump, payer = issuer, space = IdentityLink::SPACE)]
+    pub link: Account<'info, IdentityLink>,
+    /// CHECK: Holdings of the subject the token account was linked to before, may not exist (see set_link)
+    #[account(mut, seeds = [b"holdings", mint.key().as_ref(), link.subject.as_ref()], bump)]
+    pub previous_holdings: UncheckedAccount<'info>,
+    /// CHECK: Signer when the owner co-signs, receives the rent of the consent otherwise
+    #[account(mut)]
     pub owner: AccountInfo<'info>,
-    #[account(token::authority = owner.key())]
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(mut, seeds = [b"consent", token_account.key().as_ref(), issuer_record.id.as_ref()], bump, close = owner)]
+    pub consent: Option<Account<'info, IssuerConsent>>,
+    #[account(mut, token::mint = mint, token::authority = owner.key())]
     pub token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Freeze record of the token account, may not exist (see frozen_by_compliance)
+    #[account(seeds = [b"freeze_record", token_account.key().as_ref()], bump)]
+    pub freeze_record: UncheckedAccount<'info>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
@@ -19,34 +42,192 @@ pub struct InitializeId<'info> {
 pub struct AddIssuer<'info> {
     #[account(mut)]
     pub issuer: Signer<'info>,
-    #[account(seeds = [b"identity", token_account.key().as_ref()], bump)] // TODO: Add reallocate
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey, constraint = issuer_record.active @ IssuerError::IssuerNotActive)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    // Root issuer of a sub-issuer
+    #[account(seeds = [b"issuer", issuer_record.parent.as_ref()], bump)]
+    pub parent_issuer_record: Option<Account<'info, IssuerRecord>>,
+    #[account(mut, seeds = [b"identity", token_account.key().as_ref()], bump, realloc = IdAccount::space(idendity.issuers.len() + 1, idendity.recovered_token_address.len(), idendity.claims.len()), realloc::payer = issuer, realloc::zero = false)]
     pub idendity: Account<'info, IdAccount>,
-    /// CHECK:
+    /// CHECK: Signer when the owner co-signs, receives the rent of the consent otherwise
+    #[account(mut)]
     pub owner: AccountInfo<'info>,
+    #[account(seeds = [b"mint_config", token_account.mint.as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(mut, seeds = [b"consent", token_account.key().as_ref(), issuer_record.id.as_ref()], bump, close = owner)]
+    pub consent: Option<Account<'info, IssuerConsent>>,
     #[account(token::authority = owner.key())]
     pub token_account: InterfaceAccount<'info, TokenAccount>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+// The account shrinks by one issuer and its claims, the rent is given back to the issuer
+#[derive(Accounts)]
+pub struct RemoveIssuer<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    #[account(mut, seeds = [b"identity", token_account.key().as_ref()], bump, realloc = IdAccount::space(idendity.issuers.len().saturating_sub(1), idendity.recovered_token_address.len(), idendity.claims_not_from(&issuer_record.id)), realloc::payer = issuer, realloc::zero = false)]
+    pub idendity: Account<'info, IdAccount>,
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+// The owner detaches an issuer from its idendity, the rent of the entry goes back to the issuer that paid it
+#[derive(Accounts)]
+pub struct DetachIssuer<'info> {
+    pub owner: Signer<'info>,
+    /// CHECK: Only receives the rent, current key of the issuer
+    #[account(mut)]
+    pub issuer: AccountInfo<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    #[account(mut, seeds = [b"identity", token_account.key().as_ref()], bump, realloc = IdAccount::space(idendity.issuers.len().saturating_sub(1), idendity.recovered_token_address.len(), idendity.claims_not_from(&issuer_record.id)), realloc::payer = issuer, realloc::zero = false)]
+    pub idendity: Account<'info, IdAccount>,
+    #[account(token::authority = owner)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
 #[account]
 pub struct IdAccount {
-    // 8 + 68 + issuers.len() * 49  + 4 + optional(1* 32)
+    // 8 + 68 + issuers.len() * 52  + 4 + optional(1* 32) + 4 + claims.len() * 53 + 1 + 82 + 32
     pub owner: Pubkey,         // 32
     pub token_account: Pubkey, // 32
-    pub issuers: Vec<Issuer>,  // 4 + 1* 49
+    pub issuers: Vec<Issuer>,  // 4 + 1* 52
     pub recovered_token_address: Vec<Pubkey>, // 4 + optional(1* 32) We do this to only have to pay 4 bytes most of the time // TODO: maybe do manually for cheaper
                                               // recovered_address is the token account address of the new owner for this token
+    pub claims: Vec<Claim>,    // 4 + claims.len() * 53
+    pub commitment: Option<ClaimCommitment>, // 1 + 82 - Merkle root of claims kept off-chain, always allocated
+    pub payer: Pubkey,         // 32 - paid the rent at creation, refunded when the idendity is pruned
 }
 // The Idendity field "recovered_address" should be used if the account has been recovered
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct Issuer {
-    // Total 49
-    pub key: Pubkey,        // 32
+    // Total 52
+    pub key: Pubkey,        // 32 - id of the issuer (see IssuerRecord)
     pub last_modified: i64, // 8
     pub expires_at: i64,    // 8
-    pub active: bool,       // 1
+    pub active: bool,       // 1 - false when suspended
+    pub revoked: bool,      // 1 - a revoked issuer can't be reinstated
+    pub reason_code: u16,   // 2 - reason of the last suspension/reinstatement/revocation
+}
+
+impl Issuer {
+    pub fn check_valid(&self, now: i64) -> Result<()> {
+        if self.revoked {
+            return Err(IdendityError::IdendityRevoked.into());
+        }
+        if !self.active {
+            return Err(IdendityError::IdendityNotActive.into());
+        }
+        if self.expires_at < now {
+            return Err(IdendityError::IdendityExpired.into());
+        }
+        Ok(())
+    }
+}
+
+impl IdAccount {
+    pub fn space(issuers: usize, recovered_token_addresses: usize, claims: usize) -> usize {
+        8 + 32 + 32 + 4 + 52 * issuers + 4 + 32 * recovered_token_addresses + 4 + 53 * claims + 1 + 82 + 32
+    }
+
+    pub fn find_claim(&self, issuer: &Pubkey, claim_type: ClaimType) -> Option<usize> {
+        self.claims
+            .iter()
+            .position(|claim| claim.issuer == *issuer && claim.claim_type == claim_type)
+    }
+
+    // Number of claims left once the claims of this issuer are removed
+    pub fn claims_not_from(&self, issuer: &Pubkey) -> usize {
+        self.claims.iter().filter(|claim| claim.issuer != *issuer).count()
+    }
+
+    // The idendity is valid as long as one of its issuers still vouches for it
+    pub fn check_valid(&self, now: i64) -> Result<()> {
+        if self.issuers.iter().any(|issuer| issuer.check_valid(now).is_ok()) {
+            return Ok(());
+        }
+        match self.issuers.first() {
+            Some(issuer) => issuer.check_valid(now),
+            None => Err(IdendityError::IdendityNotActive.into()),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateIdendity<'info> {
+    pub issuer: Signer<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    // Root issuer of a sub-issuer
+    #[account(seeds = [b"issuer", issuer_record.parent.as_ref()], bump)]
+    pub parent_issuer_record: Option<Account<'info, IssuerRecord>>,
+    #[account(mut, seeds = [b"identity", subject.key().as_ref()], bump)]
+    pub idendity: Account<'info, IdAccount>,
+    /// CHECK: Token account of the idendity, or owner of a person idendity
+    pub subject: UncheckedAccount<'info>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetValidityBounds<'info> {
+    #[account(seeds = [b"config"], bump, has_one = super_admin @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub super_admin: Signer<'info>,
+}
+
+#[event]
+pub struct ValidityBoundsUpdated {
+    pub mint: Pubkey,
+    pub min_validity_duration: i64,
+    pub max_validity_duration: i64,
+}
+
+#[event]
+pub struct IdendityRenewed {
+    pub subject: Pubkey,
+    pub issuer: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct IdenditySuspended {
+    pub subject: Pubkey,
+    pub issuer: Pubkey,
+    pub reason_code: u16,
+}
+
+#[event]
+pub struct IdendityReinstated {
+    pub subject: Pubkey,
+    pub issuer: Pubkey,
+    pub reason_code: u16,
+}
+
+#[event]
+pub struct IdendityRevoked {
+    pub subject: Pubkey,
+    pub issuer: Pubkey,
+    pub reason_code: u16,
+}
+
+#[event]
+pub struct IssuerDetached {
+    pub token_account: Pubkey,
+    pub issuer: Pubkey,
+    pub owner: Pubkey,
 }
 
 #[error_code]
@@ -61,39 +242,247 @@ pub enum IdendityError {
     IdendityRecovered,
     #[msg("Idendity already recovered")]
     IdendityAlreadyRecovered,
+    #[msg("Idendity revoked")]
+    IdendityRevoked,
+    #[msg("Idendity already active")]
+    IdendityAlreadyActive,
+    #[msg("The issuer has not attested this idendity")]
+    IssuerNotFound,
+    #[msg("The last issuer of an idendity can't be removed")]
+    LastIssuer,
+    #[msg("This reason code is reserved to the expiration of an idendity")]
+    ReservedReasonCode,
 }
 
 pub fn _initialize_id(ctx: Context<InitializeId>, id_validity_duration: i64) -> Result<()> {
+    check_owner_consent(&ctx.accounts.mint_config, &ctx.accounts.owner, &ctx.accounts.consent)?;
+
     let clock = Clock::get()?;
+    let issuer_record = &ctx.accounts.issuer_record;
+    issuer_record.check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), clock.unix_timestamp)?;
+    issuer_record.check_validity_duration(id_validity_duration)?;
+    ctx.accounts.mint_config.check_validity_duration(id_validity_duration)?;
+    let expires_at = expires_after(clock.unix_timestamp, id_validity_duration)?;
+
     let idendity = &mut ctx.accounts.idendity;
     idendity.owner = ctx.accounts.owner.key().clone();
     idendity.token_account = ctx.accounts.token_account.key().clone();
 
     let issuer = Issuer {
-        key: ctx.accounts.issuer.key().clone(),
+        key: ctx.accounts.issuer_record.id,
         last_modified: clock.unix_timestamp,
-        expires_at: clock.unix_timestamp + id_validity_duration,
+        expires_at,
         active: true,
+        revoked: false,
+        reason_code: 0,
     };
     idendity.issuers = vec![issuer];
+    idendity.claims = vec![];
+    idendity.commitment = None;
+    idendity.payer = ctx.accounts.issuer.key();
+    // The token account leaves the person idendity it may have been linked to, its own idendity has no holdings
+    set_link(
+        &mut ctx.accounts.link,
+        ctx.accounts.token_account.key(),
+        &ctx.accounts.token_account,
+        ctx.accounts.owner.key(),
+        &ctx.accounts.previous_holdings,
+    )?;
+
+    // Token accounts are frozen by default, the attestation of the issuer unlocks the account (not a freeze ordered by Compliance)
+    if ctx.accounts.token_account.is_frozen() && !frozen_by_compliance(&ctx.accounts.freeze_record)? {
+        thaw_with_mint(
+            &ctx.accounts.token_account.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.bumps.mint,
+        )?;
+    }
     Ok(())
 }
 
 pub fn _add_issuer(ctx: Context<AddIssuer>, id_validity_duration: i64) -> Result<()> {
-    // Check if the issuer is in the list of authorized issuers or if they have a signature or smth like that
+    check_owner_consent(&ctx.accounts.mint_config, &ctx.accounts.owner, &ctx.accounts.consent)?;
+
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let issuer_record = &ctx.accounts.issuer_record;
+    issuer_record.check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), current_timestamp)?;
+    issuer_record.check_validity_duration(id_validity_duration)?;
+    ctx.accounts.mint_config.check_validity_duration(id_validity_duration)?;
+    let expires_at = expires_after(current_timestamp, id_validity_duration)?;
 
     let issuers = &mut ctx.accounts.idendity.issuers;
-    if issuers.iter().any(|i| i.key == ctx.accounts.issuer.key()) {
+    if issuers.iter().any(|i| i.key == ctx.accounts.issuer_record.id) {
         return Err(IdendityError::IdendityAlreadyExists.into());
     }
-    let current_timestamp = Clock::get()?.unix_timestamp;
     let new_issuer = Issuer {
-        key: ctx.accounts.issuer.key().clone(),
+        key: ctx.accounts.issuer_record.id,
         last_modified: current_timestamp,
-        expires_at: current_timestamp + id_validity_duration,
+        expires_at,
         active: true,
+        revoked: false,
+        reason_code: 0,
     };
     issuers.push(new_issuer);
 
     Ok(())
 }
+
+pub fn _remove_issuer(ctx: Context<RemoveIssuer>) -> Result<()> {
+    remove_issuer_entry(&mut ctx.accounts.idendity, &ctx.accounts.issuer_record.id)
+}
+
+pub fn _detach_issuer(ctx: Context<DetachIssuer>) -> Result<()> {
+    remove_issuer_entry(&mut ctx.accounts.idendity, &ctx.accounts.issuer_record.id)?;
+
+    emit!(IssuerDetached {
+        token_account: ctx.accounts.token_account.key(),
+        issuer: ctx.accounts.issuer_record.id,
+        owner: ctx.accounts.owner.key(),
+    });
+    Ok(())
+}
+
+pub fn _renew_idendity(ctx: Context<UpdateIdendity>, id_validity_duration: i64) -> Result<()> {
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let issuer_record = &ctx.accounts.issuer_record;
+    issuer_record.check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), current_timestamp)?;
+    issuer_record.check_validity_duration(id_validity_duration)?;
+    ctx.accounts.mint_config.check_validity_duration(id_validity_duration)?;
+    let expires_at = expires_after(current_timestamp, id_validity_duration)?;
+    let issuer = find_issuer(&mut ctx.accounts.idendity, &ctx.accounts.issuer_record.id)?;
+    if issuer.revoked {
+        return Err(IdendityError::IdendityRevoked.into());
+    }
+    // An issuer marked inactive by prune_expired_identity vouches again once renewed, a suspension is kept
+    if !issuer.active && issuer.reason_code == EXPIRED_REASON_CODE {
+        issuer.active = true;
+        issuer.reason_code = 0;
+    }
+    issuer.expires_at = expires_at;
+    issuer.last_modified = current_timestamp;
+
+    emit!(IdendityRenewed {
+        subject: ctx.accounts.subject.key(),
+        issuer: ctx.accounts.issuer_record.id,
+        expires_at: issuer.expires_at,
+    });
+    Ok(())
+}
+
+pub fn _set_validity_bounds(
+    ctx: Context<SetValidityBounds>,
+    min_validity_duration: i64,
+    max_validity_duration: i64,
+) -> Result<()> {
+    if min_validity_duration < 0
+        || max_validity_duration < 0
+        || (max_validity_duration > 0 && min_validity_duration > max_validity_duration)
+    {
+        return Err(ValidityError::InvalidValidityBounds.into());
+    }
+    let mint_config = &mut ctx.accounts.mint_config;
+    mint_config.min_validity_duration = min_validity_duration;
+    mint_config.max_validity_duration = max_validity_duration;
+
+    emit!(ValidityBoundsUpdated {
+        mint: ctx.accounts.mint.key(),
+        min_validity_duration,
+        max_validity_duration,
+    });
+    Ok(())
+}
+
+pub fn _suspend_idendity(ctx: Context<UpdateIdendity>, reason_code: u16) -> Result<()> {
+    if reason_code == EXPIRED_REASON_CODE {
+        return Err(IdendityError::ReservedReasonCode.into());
+    }
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let issuer = find_issuer(&mut ctx.accounts.idendity, &ctx.accounts.issuer_record.id)?;
+    if issuer.revoked {
+        return Err(IdendityError::IdendityRevoked.into());
+    }
+    if !issuer.active {
+        return Err(IdendityError::IdendityNotActive.into());
+    }
+    issuer.active = false;
+    issuer.reason_code = reason_code;
+    issuer.last_modified = current_timestamp;
+
+    emit!(IdenditySuspended {
+        subject: ctx.accounts.subject.key(),
+        issuer: ctx.accounts.issuer_record.id,
+        reason_code,
+    });
+    Ok(())
+}
+
+pub fn _reinstate_idendity(ctx: Context<UpdateIdendity>, reason_code: u16) -> Result<()> {
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts
+        .issuer_record
+        .check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), current_timestamp)?;
+    let issuer = find_issuer(&mut ctx.accounts.idendity, &ctx.accounts.issuer_record.id)?;
+    if issuer.revoked {
+        return Err(IdendityError::IdendityRevoked.into());
+    }
+    if issuer.active {
+        return Err(IdendityError::IdendityAlreadyActive.into());
+    }
+    issuer.active = true;
+    issuer.reason_code = reason_code;
+    issuer.last_modified = current_timestamp;
+
+    emit!(IdendityReinstated {
+        subject: ctx.accounts.subject.key(),
+        issuer: ctx.accounts.issuer_record.id,
+        reason_code,
+    });
+    Ok(())
+}
+
+// Revocation is permanent: the issuer can't renew nor reinstate, and can't be added back to this idendity
+pub fn _revoke_idendity(ctx: Context<UpdateIdendity>, reason_code: u16) -> Result<()> {
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let issuer = find_issuer(&mut ctx.accounts.idendity, &ctx.accounts.issuer_record.id)?;
+    if issuer.revoked {
+        return Err(IdendityError::IdendityRevoked.into());
+    }
+    issuer.active = false;
+    issuer.revoked = true;
+    issuer.reason_code = reason_code;
+    issuer.last_modified = current_timestamp;
+
+    emit!(IdendityRevoked {
+        subject: ctx.accounts.subject.key(),
+        issuer: ctx.accounts.issuer_record.id,
+        reason_code,
+    });
+    Ok(())
+}
+
+// Only one entry is removed, the account has been shrunk by one issuer and its claims.
+// An idendity always keeps an issuer: once expired, it is closed by prune_expired_idendity.
+#[inline(always)]
+fn remove_issuer_entry(idendity: &mut IdAccount, key: &Pubkey) -> Result<()> {
+    if idendity.issuers.len() <= 1 {
+        return Err(IdendityError::LastIssuer.into());
+    }
+    let index = idendity
+        .issuers
+        .iter()
+        .position(|issuer| issuer.key == *key)
+        .ok_or(IdendityError::IssuerNotFound)?;
+    idendity.issuers.remove(index);
+    idendity.claims.retain(|claim| claim.issuer != *key);
+    Ok(())
+}
+
+#[inline(always)]
+fn find_issuer<'a>(idendity: &'a mut IdAccount, key: &Pubkey) -> Result<&'a mut Issuer> {
+    idendity
+        .issuers
+        .iter_mut()
+        .find(|issuer| issuer.key == *key)
+        .ok_or(IdendityError::IssuerNotFound.into())
+}
@@ -0,0 +1,232 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{check_issuer_chain, expires_after, frozen_by_compliance, remove_from_holdings, thaw_with_mint, IdAccount, IdentityHoldings, Issuer, IssuerError, IssuerRecord};
+
+// Links a token account to the idendity covering it, followed by the transfer hook.
+// A person idendity is seeded by its owner and covers all the token accounts it links, of any mint.
+// An institution idendity is seeded by its entity key (see Institution).
+// An idendity issued for a single token account is seeded by the token account and linked to it at creation.
+#[account]
+pub struct IdentityLink {
+    pub subject: Pubkey,       // 32 - seed of the idendity: the owner of a person idendity, the token account otherwise (read by the transfer hook at offset 8)
+    pub token_account: Pubkey, // 32
+    pub owner: Pubkey,         // 32 - owner of the token account when it was linked
+}
+
+impl IdentityLink {
+    pub const SPACE: usize = 8 + 32 + 32 + 32;
+}
+
+// The owner signs: a person idendity covers every account it will link
+#[derive(Accounts)]
+pub struct InitializePersonId<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey, constraint = issuer_record.active @ IssuerError::IssuerNotActive)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    // Root issuer of a sub-issuer
+    #[account(seeds = [b"issuer", issuer_record.parent.as_ref()], bump)]
+    pub parent_issuer_record: Option<Account<'info, IssuerRecord>>,
+    #[account(init, seeds = [b"identity", owner.key().as_ref()], bump, payer = issuer, space = IdAccount::space(1, 0, 0))]
+    pub idendity: Account<'info, IdAccount>,
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LinkTokenAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"identity", owner.key().as_ref()], bump, has_one = owner)]
+    pub idendity: Account<'info, IdAccount>,
+    #[account(init_if_needed, seeds = [b"identity_link", token_account.key().as_ref()], bump, payer = owner, space = IdentityLink::SPACE)]
+    pub link: Account<'info, IdentityLink>,
+    /// CHECK: Holdings of the subject the token account was linked to before, may not exist (see set_link)
+    #[account(mut, seeds = [b"holdings", mint.key().as_ref(), link.subject.as_ref()], bump)]
+    pub previous_holdings: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(init_if_needed, seeds = [b"holdings", mint.key().as_ref(), owner.key().as_ref()], bump, payer = owner, space = IdentityHoldings::SPACE)]
+    pub holdings: Account<'info, IdentityHoldings>,
+    #[account(mut, token::mint = mint, token::authority = owner)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Freeze record of the token account, may not exist (see frozen_by_compliance)
+    #[account(seeds = [b"freeze_record", token_account.key().as_ref()], bump)]
+    pub freeze_record: UncheckedAccount<'info>,
+    /// CHECK: Issuer record of the first issuer of the idendity (see check_issuer_chain)
+    pub issuer_record: UncheckedAccount<'info>,
+    /// CHECK: Root issuer record of issuer_record, does not exist for a root issuer
+    pub root_issuer_record: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnlinkTokenAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"identity_link", token_account.key().as_ref()], bump, constraint = link.subject == owner.key() @ LinkError::NotPersonLink, close = owner)]
+    pub link: Account<'info, IdentityLink>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"holdings", mint.key().as_ref(), owner.key().as_ref()], bump)]
+    pub holdings: Account<'info, IdentityHoldings>,
+    #[account(token::mint = mint, token::authority = owner)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+}
+
+#[event]
+pub struct PersonIdendityAdded {
+    pub owner: Pubkey,
+    pub issuer: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct TokenAccountLinked {
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+    pub idendity: Pubkey,
+}
+
+#[event]
+pub struct TokenAccountUnlinked {
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+}
+
+#[error_code]
+pub enum LinkError {
+    #[msg("The idendity does not cover this token account")]
+    IdendityNotLinked,
+    #[msg("The token account is not linked to the person idendity of its owner")]
+    NotPersonLink,
+}
+
+pub fn _initialize_person_id(ctx: Context<InitializePersonId>, id_validity_duration: i64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let issuer_record = &ctx.accounts.issuer_record;
+    issuer_record.check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), now)?;
+    issuer_record.check_validity_duration(id_validity_duration)?;
+    let expires_at = expires_after(now, id_validity_duration)?;
+
+    let idendity = &mut ctx.accounts.idendity;
+    idendity.owner = ctx.accounts.owner.key();
+    idendity.token_account = Pubkey::default();
+    idendity.issuers = vec![Issuer {
+        key: ctx.accounts.issuer_record.id,
+        last_modified: now,
+        expires_at,
+        active: true,
+        revoked: false,
+        reason_code: 0,
+    }];
+    idendity.recovered_token_address = vec![];
+    idendity.claims = vec![];
+    idendity.commitment = None;
+    idendity.payer = ctx.accounts.issuer.key();
+
+    emit!(PersonIdendityAdded {
+        owner: ctx.accounts.owner.key(),
+        issuer: ctx.accounts.issuer_record.id,
+        expires_at,
+    });
+    Ok(())
+}
+
+pub fn _link_token_account(ctx: Context<LinkTokenAccount>) -> Result<()> {
+    let first_link = ctx.accounts.link.token_account == Pubkey::default();
+    let new_subject = set_link(
+        &mut ctx.accounts.link,
+        ctx.accounts.owner.key(),
+        &ctx.accounts.token_account,
+        ctx.accounts.owner.key(),
+        &ctx.accounts.previous_holdings,
+    )?;
+
+    if new_subject {
+        let holdings = &mut ctx.accounts.holdings;
+        holdings.subject = ctx.accounts.owner.key();
+        holdings.amount = holdings.amount.saturating_add(ctx.accounts.token_account.amount);
+    }
+
+    // Token accounts are frozen by default, like an attestation the first link unlocks the account (not a freeze ordered by Compliance)
+    if first_link && ctx.accounts.token_account.is_frozen() && !frozen_by_compliance(&ctx.accounts.freeze_record)? {
+        // Only an idendity the transfer hook would accept unlocks it
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.idendity.check_valid(now)?;
+        check_issuer_chain(&ctx.accounts.idendity, &ctx.accounts.issuer_record, &ctx.accounts.root_issuer_record, now)?;
+        thaw_with_mint(
+            &ctx.accounts.token_account.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.bumps.mint,
+        )?;
+    }
+
+    emit!(TokenAccountLinked {
+        owner: ctx.accounts.owner.key(),
+        token_account: ctx.accounts.token_account.key(),
+        idendity: ctx.accounts.idendity.key(),
+    });
+    Ok(())
+}
+
+pub fn _unlink_token_account(ctx: Context<UnlinkTokenAccount>) -> Result<()> {
+    let holdings = &mut ctx.accounts.holdings;
+    holdings.amount = holdings.amount.saturating_sub(ctx.accounts.token_account.amount);
+
+    emit!(TokenAccountUnlinked {
+        owner: ctx.accounts.owner.key(),
+        token_account: ctx.accounts.token_account.key(),
+    });
+    Ok(())
+}
+
+// Points the link of a token account at `subject`. A token account linked to another subject before leaves the holdings
+// of that subject with its balance: returns true when the balance must be added to the holdings of `subject`.
+pub fn set_link(
+    link: &mut IdentityLink,
+    subject: Pubkey,
+    token_account: &InterfaceAccount<TokenAccount>,
+    owner: Pubkey,
+    previous_holdings: &AccountInfo,
+) -> Result<bool> {
+    let first_link = link.token_account == Pubkey::default();
+    let new_subject = first_link || link.subject != subject;
+    if !first_link && new_subject {
+        remove_from_holdings(previous_holdings, token_account.amount)?;
+    }
+    link.subject = subject;
+    link.token_account = token_account.key();
+    link.owner = owner;
+    Ok(new_subject)
+}
+
+// Token accounts are not linked until an idendity covers them
+pub fn load_link(account: &AccountInfo) -> Result<Option<IdentityLink>> {
+    if account.owner != &crate::ID || account.data_is_empty() {
+        return Ok(None);
+    }
+    let data = account.try_borrow_data()?;
+    Ok(Some(IdentityLink::try_deserialize(&mut &data[..])?))
+}
+
+// A link only holds while the token account keeps the owner it had when it was linked
+pub fn check_linked(
+    link: &IdentityLink,
+    idendity: &IdAccount,
+    token_account: &TokenAccount,
+    token_account_key: &Pubkey,
+) -> Result<()> {
+    let linked = if idendity.token_account == Pubkey::default() {
+        link.owner == token_account.owner
+    } else {
+        idendity.token_account == *token_account_key
+    };
+    if !linked {
+        return Err(LinkError::IdendityNotLinked.into());
+    }
+    Ok(())
+}
@@ -1,5 +1,53 @@
-use anchor_lang::{prelude::*, solana_program::{program, system_instruction}};
-use anchor_spl::{token_2022::spl_token_2022::{self, extension::ExtensionType, state::Mint}, token_interface::{TokenInterface}};
+use anchor_lang::{prelude::*, solana_program::{program, system_instruction}, system_program::{transfer, Transfer}, Discriminator};
+use anchor_spl::{token_2022::spl_token_2022::{self, extension::ExtensionType, state::{AccountState, Mint}}, token_interface::{Mint as MintAccount, TokenInterface}};
+
+use crate::{BalanceCaps, ClaimRequirement, ConfigError, HolderCount, JurisdictionMatrix, ProgramConfig, ValidityError, MAX_PAUSE_EXEMPTIONS, MAX_REQUIRED_CLAIMS};
+
+// Per mint configuration, read by the transfer hook
+#[account]
+pub struct MintConfig {
+    pub paused: bool,                     // 1
+    // Token accounts that can still transfer when paused (treasury, redemption...)
+    pub pause_exemptions: Vec<Pubkey>,    // 4 + 32 * MAX_PAUSE_EXEMPTIONS
+    // Layout of the transfer hook accounts in the ExtraAccountMetaList (0: not initialized)
+    pub extra_account_metas_version: u16, // 2
+    // The token account owner has to co-sign, or consent to the issuer, for an issuer to attest its idendity
+    pub owner_consent_required: bool,     // 1
+    // Claims the sender and/or the receiver must hold (checked in the transfer hook)
+    pub required_claims: Vec<ClaimRequirement>, // 4 + 5 * MAX_REQUIRED_CLAIMS
+    // Bounds of the validity of the idendities of this mint (0: no bound)
+    pub min_validity_duration: i64,       // 8
+    pub max_validity_duration: i64,       // 8
+    // Caps on the balance of the idendities, read by the transfer hook and mint_to
+    pub balance_caps: BalanceCaps,        // BalanceCaps::SPACE
+    pub holder_count: HolderCount,        // HolderCount::SPACE
+}
+
+// Layout of MintConfig before the balance caps and the holder count were added to it, read by migrate_mint_config
+#[derive(AnchorDeserialize)]
+pub struct LegacyMintConfig {
+    pub paused: bool,
+    pub pause_exemptions: Vec<Pubkey>,
+    pub extra_account_metas_version: u16,
+    pub owner_consent_required: bool,
+    pub required_claims: Vec<ClaimRequirement>,
+    pub min_validity_duration: i64,
+    pub max_validity_duration: i64,
+}
+
+impl MintConfig {
+    pub const SPACE: usize = 8 + 1 + 4 + 32 * MAX_PAUSE_EXEMPTIONS + 2 + 1 + 4 + 5 * MAX_REQUIRED_CLAIMS + 8 + 8 + BalanceCaps::SPACE + HolderCount::SPACE;
+
+    pub fn check_validity_duration(&self, validity_duration: i64) -> Result<()> {
+        if validity_duration < self.min_validity_duration {
+            return Err(ValidityError::BelowMintMinValidity.into());
+        }
+        if self.max_validity_duration > 0 && validity_duration > self.max_validity_duration {
+            return Err(ValidityError::AboveMintMaxValidity.into());
+        }
+        Ok(())
+    }
+}
 
 #[derive(Accounts)]
 pub struct InitializeTokenMint<'info> {
@@ -15,17 +63,46 @@ pub struct InitializeTokenMint<'info> {
     pub mint: UncheckedAccount<'info>,
     #[account(mut)]
     pub payer: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.super_admin == payer.key() @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(init, seeds = [b"mint_config", mint.key().as_ref()], bump, payer = payer, space = MintConfig::SPACE)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(init, seeds = [b"jurisdiction", mint.key().as_ref()], bump, payer = payer, space = JurisdictionMatrix::SPACE)]
+    pub jurisdiction_matrix: Account<'info, JurisdictionMatrix>,
     pub token_program: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
 }
 
+// MintConfig has a fixed size: a mint created by an older version of the program has its MintConfig grown to the current layout
+#[derive(Accounts)]
+pub struct MigrateMintConfig<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.super_admin == payer.key() @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    /// CHECK: MintConfig in the legacy layout (see LegacyMintConfig), it can't be deserialized as a MintConfig
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump, owner = crate::ID)]
+    pub mint_config: UncheckedAccount<'info>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, MintAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[event]
+pub struct MintConfigMigrated {
+    pub mint: Pubkey,
+    pub size: u64,
+}
+
 #[error_code]
 pub enum MintError {
     #[msg("Invalid Mint Account: the mint account in the accounts passed as arguments is not the expected one.")]
     InvalidMintAccount,
     #[msg("Invalid Token Program: the token program in the accounts passed as arguments is not the expected one.")]
     InvalidTokenProgram,
+    #[msg("The mint config already has the current layout")]
+    MintConfigAlreadyMigrated,
 }
 
 
@@ -47,16 +124,80 @@ pub fn _initialize_token_mint(ctx: Context<InitializeTokenMint>) -> Result<()> {
         create_account(&ctx, seeds)?;
         init_transfer_hook(&ctx, seeds)?;
         init_permanent_delegate(&ctx, seeds)?;
+        init_default_account_state(&ctx, seeds)?;
         init_mint(&ctx, seeds)?;
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.paused = false;
+        mint_config.pause_exemptions = vec![];
+        mint_config.extra_account_metas_version = 0;
+        mint_config.owner_consent_required = false;
+        mint_config.required_claims = vec![];
+        mint_config.min_validity_duration = 0;
+        mint_config.max_validity_duration = 0;
+        mint_config.balance_caps = BalanceCaps::no_cap();
+        mint_config.holder_count = HolderCount::default();
+
+        ctx.accounts.jurisdiction_matrix.set_inner(JurisdictionMatrix::empty());
+
         
         Ok(())
 }
 
+// The fields added since the legacy layout get their values of a new mint.
+// The mint may already have holders: the super admin seeds the holder count with a recount (see start_holder_recount).
+pub fn _migrate_mint_config(ctx: Context<MigrateMintConfig>) -> Result<()> {
+    let mint_config = &ctx.accounts.mint_config;
+    if mint_config.data_len() >= MintConfig::SPACE {
+        return Err(MintError::MintConfigAlreadyMigrated.into());
+    }
+    let legacy = {
+        let data = mint_config.try_borrow_data()?;
+        if data.len() < 8 || data[..8] != MintConfig::DISCRIMINATOR {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        LegacyMintConfig::deserialize(&mut &data[8..])?
+    };
+
+    let lamports = Rent::get()?.minimum_balance(MintConfig::SPACE);
+    let missing_lamports = lamports.saturating_sub(mint_config.lamports());
+    if missing_lamports > 0 {
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: mint_config.to_account_info(),
+                },
+            ),
+            missing_lamports,
+        )?;
+    }
+    mint_config.realloc(MintConfig::SPACE, true)?;
+
+    let migrated = MintConfig {
+        paused: legacy.paused,
+        pause_exemptions: legacy.pause_exemptions,
+        extra_account_metas_version: legacy.extra_account_metas_version,
+        owner_consent_required: legacy.owner_consent_required,
+        required_claims: legacy.required_claims,
+        min_validity_duration: legacy.min_validity_duration,
+        max_validity_duration: legacy.max_validity_duration,
+        balance_caps: BalanceCaps::no_cap(),
+        holder_count: HolderCount::default(),
+    };
+    migrated.try_serialize(&mut &mut mint_config.try_borrow_mut_data()?[..])?;
 
+    emit!(MintConfigMigrated {
+        mint: ctx.accounts.mint.key(),
+        size: MintConfig::SPACE as u64,
+    });
+    Ok(())
+}
 
 #[inline(always)]
 pub fn create_account(ctx: &Context<InitializeTokenMint>, seeds : &[&[&[u8]]]) -> Result<()>{
-    let extensions = [ExtensionType::TransferHook, ExtensionType::PermanentDelegate]; //, ExtensionType::MetadataPointer];
+    let extensions = [ExtensionType::TransferHook, ExtensionType::PermanentDelegate, ExtensionType::DefaultAccountState]; //, ExtensionType::MetadataPointer];
     let space =  ExtensionType::try_calculate_account_len::<Mint>(&extensions)?;
     let mint_rent = Rent::default().minimum_balance(space);
 
@@ -118,6 +259,27 @@ pub fn init_permanent_delegate(ctx: &Context<InitializeTokenMint>, seeds : &[&[&
     Ok(())
 }
 
+// New token accounts start frozen: they are thawed by the mint (freeze authority) once an idendity is issued
+#[inline(always)]
+pub fn init_default_account_state(ctx: &Context<InitializeTokenMint>, seeds : &[&[&[u8]]]) -> Result<()>{
+    let ix = spl_token_2022::extension::default_account_state::instruction::initialize_default_account_state(
+        &spl_token_2022::id(),
+        ctx.accounts.mint.key,
+        &AccountState::Frozen
+    )?;
+
+    program::invoke_signed(
+        &ix,
+        &[
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ],
+        seeds,
+    )?;
+
+    Ok(())
+}
+
 #[inline(always)]
 pub fn init_mint(ctx: &Context<InitializeTokenMint>, seeds : &[&[&[u8]]]) -> Result<()>{
     let ix = spl_token_2022::instruction::initialize_mint(
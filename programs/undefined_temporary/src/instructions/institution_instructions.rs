@@ -0,0 +1,371 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    check_issuer_chain, expires_after, frozen_by_compliance, set_link, thaw_with_mint, token_balance,
+    IdAccount, IdentityHoldings, IdentityLink, Issuer, IssuerError, IssuerRecord, LastTx,
+    TokenAccountLinked, TokenAccountUnlinked, TransactionAproval, TwoAuthFunction, TwoAuthParameters,
+    MAX_AUTHORIZED_SIGNERS, MAX_INSTITUTION_VAULTS,
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq)]
+pub struct ProgramVault {
+    // 32 + 32 = 64
+    pub program_id: Pubkey, // 32
+    pub owner: Pubkey,      // 32 - PDA of program_id owning the vault token accounts
+}
+
+// A legal entity or a protocol: its idendity is seeded by the entity key and covers the token accounts
+// owned by its authorized signers and by the program-owned vaults it registered (escrows, AMM pools...)
+#[account]
+pub struct Institution {
+    pub entity: Pubkey,                  // 32
+    pub authorized_signers: Vec<Pubkey>, // 4 + 32 * MAX_AUTHORIZED_SIGNERS
+    pub vaults: Vec<ProgramVault>,       // 4 + 64 * MAX_INSTITUTION_VAULTS
+}
+
+impl Institution {
+    pub const SPACE: usize = 8 + 32 + 4 + 32 * MAX_AUTHORIZED_SIGNERS + 4 + 64 * MAX_INSTITUTION_VAULTS;
+
+    pub fn is_authorized(&self, owner: &Pubkey) -> bool {
+        self.entity == *owner
+            || self.authorized_signers.contains(owner)
+            || self.vaults.iter().any(|vault| vault.owner == *owner)
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeInstitutionId<'info> {
+    #[account(mut)]
+    pub issuer: Signer<'info>,
+    #[account(seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey, constraint = issuer_record.active @ IssuerError::IssuerNotActive)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    // Root issuer of a sub-issuer
+    #[account(seeds = [b"issuer", issuer_record.parent.as_ref()], bump)]
+    pub parent_issuer_record: Option<Account<'info, IssuerRecord>>,
+    #[account(init, seeds = [b"identity", entity.key().as_ref()], bump, payer = issuer, space = IdAccount::space(1, 0, 0))]
+    pub idendity: Account<'info, IdAccount>,
+    #[account(init, seeds = [b"institution", entity.key().as_ref()], bump, payer = entity, space = Institution::SPACE)]
+    pub institution: Account<'info, Institution>,
+    #[account(mut)]
+    pub entity: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAuthorizedSigners<'info> {
+    pub entity: Signer<'info>,
+    #[account(mut, seeds = [b"institution", entity.key().as_ref()], bump, has_one = entity)]
+    pub institution: Account<'info, Institution>,
+}
+
+// An authorized signer links a token account it owns to the idendity of the institution
+#[derive(Accounts)]
+pub struct LinkInstitutionTokenAccount<'info> {
+    #[account(mut, constraint = institution.is_authorized(&signer.key()) @ InstitutionError::NotAuthorizedSigner)]
+    pub signer: Signer<'info>,
+    #[account(seeds = [b"institution", institution.entity.as_ref()], bump)]
+    pub institution: Account<'info, Institution>,
+    #[account(seeds = [b"identity", institution.entity.as_ref()], bump)]
+    pub idendity: Account<'info, IdAccount>,
+    #[account(init_if_needed, seeds = [b"identity_link", token_account.key().as_ref()], bump, payer = signer, space = IdentityLink::SPACE)]
+    pub link: Account<'info, IdentityLink>,
+    /// CHECK: Holdings of the subject the token account was linked to before, may not exist (see set_link)
+    #[account(mut, seeds = [b"holdings", mint.key().as_ref(), link.subject.as_ref()], bump)]
+    pub previous_holdings: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(init_if_needed, seeds = [b"holdings", mint.key().as_ref(), institution.entity.as_ref()], bump, payer = signer, space = IdentityHoldings::SPACE)]
+    pub holdings: Account<'info, IdentityHoldings>,
+    #[account(mut, token::mint = mint, token::authority = signer)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Freeze record of the token account, may not exist (see frozen_by_compliance)
+    #[account(seeds = [b"freeze_record", token_account.key().as_ref()], bump)]
+    pub freeze_record: UncheckedAccount<'info>,
+    /// CHECK: Issuer record of the first issuer of the idendity (see check_issuer_chain)
+    pub issuer_record: UncheckedAccount<'info>,
+    /// CHECK: Root issuer record of issuer_record, does not exist for a root issuer
+    pub root_issuer_record: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// A vault is owned by a PDA: the vault program consents by signing for it in a CPI, the entity proves the owner derives
+// from the program id and seeds and creates the accounts the transfer hook reads for the owner.
+#[derive(Accounts)]
+pub struct LinkProgramVault<'info> {
+    #[account(mut)]
+    pub entity: Signer<'info>,
+    // PDA of the vault program, signed with invoke_signed
+    pub vault_owner: Signer<'info>,
+    #[account(mut, seeds = [b"institution", entity.key().as_ref()], bump, has_one = entity)]
+    pub institution: Account<'info, Institution>,
+    #[account(seeds = [b"identity", entity.key().as_ref()], bump)]
+    pub idendity: Account<'info, IdAccount>,
+    #[account(init_if_needed, seeds = [b"identity_link", token_account.key().as_ref()], bump, payer = entity, space = IdentityLink::SPACE)]
+    pub link: Account<'info, IdentityLink>,
+    /// CHECK: Program owning the vault, the owner of the token account must be one of its PDAs
+    #[account(executable)]
+    pub vault_program: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(init_if_needed, seeds = [b"holdings", mint.key().as_ref(), entity.key().as_ref()], bump, payer = entity, space = IdentityHoldings::SPACE)]
+    pub holdings: Account<'info, IdentityHoldings>,
+    #[account(mut, token::mint = mint, token::authority = vault_owner)]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Freeze record of the token account, may not exist (see frozen_by_compliance)
+    #[account(seeds = [b"freeze_record", token_account.key().as_ref()], bump)]
+    pub freeze_record: UncheckedAccount<'info>,
+    /// CHECK: Issuer record of the first issuer of the idendity (see check_issuer_chain)
+    pub issuer_record: UncheckedAccount<'info>,
+    /// CHECK: Root issuer record of issuer_record, does not exist for a root issuer
+    pub root_issuer_record: UncheckedAccount<'info>,
+    #[account(init_if_needed, seeds = [b"last_tx", token_account.owner.as_ref()], bump, payer = entity, space = 8 + 8 + 1)]
+    pub last_tx: Account<'info, LastTx>,
+    #[account(init_if_needed, seeds = [b"two_auth", token_account.key().as_ref()], bump, payer = entity, space = 8 + 4 + 11 + 32 + 4)]
+    pub two_auth: Account<'info, TwoAuthParameters>,
+    #[account(init_if_needed, seeds = [b"transaction_approval", token_account.owner.as_ref()], bump, payer = entity, space = 8 + 81)]
+    pub transaction_approval: Account<'info, TransactionAproval>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnlinkInstitutionTokenAccount<'info> {
+    #[account(mut)]
+    pub entity: Signer<'info>,
+    #[account(mut, seeds = [b"identity_link", token_account.key().as_ref()], bump, constraint = link.subject == entity.key() @ InstitutionError::NotInstitutionLink, close = entity)]
+    pub link: Account<'info, IdentityLink>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"holdings", mint.key().as_ref(), entity.key().as_ref()], bump)]
+    pub holdings: Account<'info, IdentityHoldings>,
+    /// CHECK: The token account may have been closed, its balance is read in the instruction
+    pub token_account: UncheckedAccount<'info>,
+}
+
+#[event]
+pub struct InstitutionIdendityAdded {
+    pub entity: Pubkey,
+    pub issuer: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct AuthorizedSignersUpdated {
+    pub entity: Pubkey,
+    pub authorized_signers: Vec<Pubkey>,
+}
+
+#[event]
+pub struct ProgramVaultLinked {
+    pub entity: Pubkey,
+    pub program_id: Pubkey,
+    pub owner: Pubkey,
+    pub token_account: Pubkey,
+}
+
+#[error_code]
+pub enum InstitutionError {
+    #[msg("Too many authorized signers")]
+    TooManyAuthorizedSigners,
+    #[msg("Too many program vaults")]
+    TooManyVaults,
+    #[msg("The owner of the token account is not authorized by the institution")]
+    NotAuthorizedSigner,
+    #[msg("The owner of the token account is not derived from the program id and seeds")]
+    VaultNotDerived,
+    #[msg("The token account is not linked to this institution")]
+    NotInstitutionLink,
+    #[msg("The token account is already linked to another idendity")]
+    LinkedToAnotherSubject,
+}
+
+pub fn _initialize_institution_id(
+    ctx: Context<InitializeInstitutionId>,
+    id_validity_duration: i64,
+    authorized_signers: Vec<Pubkey>,
+) -> Result<()> {
+    if authorized_signers.len() > MAX_AUTHORIZED_SIGNERS {
+        return Err(InstitutionError::TooManyAuthorizedSigners.into());
+    }
+    let now = Clock::get()?.unix_timestamp;
+    let issuer_record = &ctx.accounts.issuer_record;
+    issuer_record.check_can_attest(ctx.accounts.parent_issuer_record.as_deref(), now)?;
+    issuer_record.check_validity_duration(id_validity_duration)?;
+    let expires_at = expires_after(now, id_validity_duration)?;
+
+    let idendity = &mut ctx.accounts.idendity;
+    idendity.owner = ctx.accounts.entity.key();
+    idendity.token_account = Pubkey::default();
+    idendity.issuers = vec![Issuer {
+        key: ctx.accounts.issuer_record.id,
+        last_modified: now,
+        expires_at,
+        active: true,
+        revoked: false,
+        reason_code: 0,
+    }];
+    idendity.recovered_token_address = vec![];
+    idendity.claims = vec![];
+    idendity.commitment = None;
+    idendity.payer = ctx.accounts.issuer.key();
+
+    let institution = &mut ctx.accounts.institution;
+    institution.entity = ctx.accounts.entity.key();
+    institution.authorized_signers = authorized_signers;
+    institution.vaults = vec![];
+
+    emit!(InstitutionIdendityAdded {
+        entity: ctx.accounts.entity.key(),
+        issuer: ctx.accounts.issuer_record.id,
+        expires_at,
+    });
+    Ok(())
+}
+
+pub fn _set_authorized_signers(ctx: Context<SetAuthorizedSigners>, authorized_signers: Vec<Pubkey>) -> Result<()> {
+    if authorized_signers.len() > MAX_AUTHORIZED_SIGNERS {
+        return Err(InstitutionError::TooManyAuthorizedSigners.into());
+    }
+    ctx.accounts.institution.authorized_signers = authorized_signers.clone();
+
+    emit!(AuthorizedSignersUpdated {
+        entity: ctx.accounts.entity.key(),
+        authorized_signers,
+    });
+    Ok(())
+}
+
+pub fn _link_institution_token_account(ctx: Context<LinkInstitutionTokenAccount>) -> Result<()> {
+    let first_link = ctx.accounts.link.token_account == Pubkey::default();
+    let new_subject = set_link(
+        &mut ctx.accounts.link,
+        ctx.accounts.institution.entity,
+        &ctx.accounts.token_account,
+        ctx.accounts.signer.key(),
+        &ctx.accounts.previous_holdings,
+    )?;
+
+    if new_subject {
+        let holdings = &mut ctx.accounts.holdings;
+        holdings.subject = ctx.accounts.institution.entity;
+        holdings.amount = holdings.amount.saturating_add(ctx.accounts.token_account.amount);
+    }
+
+    if first_link && ctx.accounts.token_account.is_frozen() && !frozen_by_compliance(&ctx.accounts.freeze_record)? {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.idendity.check_valid(now)?;
+        check_issuer_chain(&ctx.accounts.idendity, &ctx.accounts.issuer_record, &ctx.accounts.root_issuer_record, now)?;
+        thaw_with_mint(
+            &ctx.accounts.token_account.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.bumps.mint,
+        )?;
+    }
+
+    emit!(TokenAccountLinked {
+        owner: ctx.accounts.signer.key(),
+        token_account: ctx.accounts.token_account.key(),
+        idendity: ctx.accounts.idendity.key(),
+    });
+    Ok(())
+}
+
+// The seeds include the bump of the vault owner
+pub fn _link_program_vault(ctx: Context<LinkProgramVault>, vault_seeds: Vec<Vec<u8>>) -> Result<()> {
+    let program_id = ctx.accounts.vault_program.key();
+    let owner = ctx.accounts.token_account.owner;
+    let seeds: Vec<&[u8]> = vault_seeds.iter().map(|seed| seed.as_slice()).collect();
+    let derived = Pubkey::create_program_address(&seeds, &program_id)
+        .map_err(|_| InstitutionError::VaultNotDerived)?;
+    if derived != owner {
+        return Err(InstitutionError::VaultNotDerived.into());
+    }
+
+    let vault = ProgramVault { program_id, owner };
+    let institution = &mut ctx.accounts.institution;
+    if !institution.vaults.contains(&vault) {
+        if institution.vaults.len() >= MAX_INSTITUTION_VAULTS {
+            return Err(InstitutionError::TooManyVaults.into());
+        }
+        institution.vaults.push(vault);
+    }
+
+    // A vault linked to another idendity must be unlinked by it first
+    let link = &mut ctx.accounts.link;
+    let first_link = link.token_account == Pubkey::default();
+    if !first_link && link.subject != ctx.accounts.entity.key() {
+        return Err(InstitutionError::LinkedToAnotherSubject.into());
+    }
+    link.subject = ctx.accounts.entity.key();
+    link.token_account = ctx.accounts.token_account.key();
+    link.owner = owner;
+
+    if first_link {
+        let holdings = &mut ctx.accounts.holdings;
+        holdings.subject = ctx.accounts.entity.key();
+        holdings.amount = holdings.amount.saturating_add(ctx.accounts.token_account.amount);
+    }
+
+    // The vault program moves the tokens, no one can approve its transfers. Parameters set before are kept.
+    let two_auth = &mut ctx.accounts.two_auth;
+    if two_auth.functions.is_empty() && two_auth.two_auth_entity == Pubkey::default() {
+        two_auth.functions = vec![TwoAuthFunction::Never];
+        two_auth.two_auth_entity = ctx.accounts.entity.key();
+        two_auth.allowed_issuers = vec![];
+    }
+
+    if first_link && ctx.accounts.token_account.is_frozen() && !frozen_by_compliance(&ctx.accounts.freeze_record)? {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.idendity.check_valid(now)?;
+        check_issuer_chain(&ctx.accounts.idendity, &ctx.accounts.issuer_record, &ctx.accounts.root_issuer_record, now)?;
+        thaw_with_mint(
+            &ctx.accounts.token_account.to_account_info(),
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            ctx.bumps.mint,
+        )?;
+    }
+
+    emit!(ProgramVaultLinked {
+        entity: ctx.accounts.entity.key(),
+        program_id,
+        owner,
+        token_account: ctx.accounts.token_account.key(),
+    });
+    Ok(())
+}
+
+pub fn _unlink_institution_token_account(ctx: Context<UnlinkInstitutionTokenAccount>) -> Result<()> {
+    let balance = token_balance(&ctx.accounts.token_account)?;
+    let holdings = &mut ctx.accounts.holdings;
+    holdings.amount = holdings.amount.saturating_sub(balance);
+
+    emit!(TokenAccountUnlinked {
+        owner: ctx.accounts.entity.key(),
+        token_account: ctx.accounts.token_account.key(),
+    });
+    Ok(())
+}
+
+// The institution of an idendity only exists for institution idendities, its signers or vaults may have been removed since the link
+pub fn check_institution_link(link: &IdentityLink, institution: &AccountInfo) -> Result<()> {
+    let institution = match load_institution(institution)? {
+        Some(institution) if institution.entity == link.subject => institution,
+        _ => return Ok(()),
+    };
+    if !institution.is_authorized(&link.owner) {
+        return Err(InstitutionError::NotAuthorizedSigner.into());
+    }
+    Ok(())
+}
+
+pub fn load_institution(account: &AccountInfo) -> Result<Option<Institution>> {
+    if account.owner != &crate::ID || account.data_is_empty() {
+        return Ok(None);
+    }
+    let data = account.try_borrow_data()?;
+    Ok(Some(Institution::try_deserialize(&mut &data[..])?))
+}
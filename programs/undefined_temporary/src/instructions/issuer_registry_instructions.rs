@@ -0,0 +1,343 @@
+use anchor_lang::prelude::*;
+
+use crate::{ClaimType, ConfigError, ProgramConfig, Role, ValidityError, MAX_ISSUER_KEY_HISTORY, MAX_SCOPE_JURISDICTIONS};
+
+// What an issuer is allowed to attest. Root issuers have every claim type, every jurisdiction and no max validity.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct IssuerScope {
+    // Total 1 + 8 + 4 + 2 * MAX_SCOPE_JURISDICTIONS
+    pub claim_types: u8,             // 1 - bit (ClaimType as u8) set when the issuer can attest this claim type
+    pub max_validity_duration: i64,  // 8 - 0: no limit
+    pub jurisdictions: Vec<u16>,     // 4 + 2 * MAX_SCOPE_JURISDICTIONS - country claims the issuer can attest, empty: all
+}
+
+impl IssuerScope {
+    pub fn all() -> Self {
+        IssuerScope {
+            claim_types: u8::MAX,
+            max_validity_duration: 0,
+            jurisdictions: vec![],
+        }
+    }
+}
+
+// Only the issuers registered here (by an issuer admin) can attest idendities.
+// A root issuer can delegate to sub-issuers (regional signing keys), that are only valid while their root is active.
+// Idendities reference the stable id of the issuer, the signing key can be rotated without touching them.
+#[account]
+pub struct IssuerRecord {
+    pub id: Pubkey,                 // 32 - first key of the issuer, seed of the record
+    pub parent: Pubkey,             // 32 - id of the root issuer of a sub-issuer, Pubkey::default() for a root issuer (read by the transfer hook at offset 40)
+    pub key: Pubkey,                // 32 - current signing key
+    pub active: bool,               // 1
+    pub registered_at: i64,         // 8
+    pub last_modified: i64,         // 8
+    pub expires_at: i64,            // 8 - i64::MAX for a root issuer
+    pub scope: IssuerScope,         // 1 + 8 + 4 + 2 * MAX_SCOPE_JURISDICTIONS
+    pub previous_keys: Vec<Pubkey>, // 4 + 32 * MAX_ISSUER_KEY_HISTORY - oldest first
+}
+
+impl IssuerRecord {
+    pub const SPACE: usize = 8 + 32 + 32 + 32 + 1 + 8 + 8 + 8 + 1 + 8 + 4 + 2 * MAX_SCOPE_JURISDICTIONS + 4 + 32 * MAX_ISSUER_KEY_HISTORY;
+
+    pub fn is_root(&self) -> bool {
+        self.parent == Pubkey::default()
+    }
+
+    // The parent record must be given for a sub-issuer
+    pub fn check_can_attest(&self, parent: Option<&IssuerRecord>, now: i64) -> Result<()> {
+        if !self.active {
+            return Err(IssuerError::IssuerNotActive.into());
+        }
+        if self.expires_at < now {
+            return Err(IssuerError::IssuerExpired.into());
+        }
+        if self.is_root() {
+            return Ok(());
+        }
+        match parent {
+            Some(parent) if parent.id == self.parent && parent.active && parent.expires_at >= now => Ok(()),
+            _ => Err(IssuerError::ParentIssuerNotActive.into()),
+        }
+    }
+
+    pub fn check_validity_duration(&self, validity_duration: i64) -> Result<()> {
+        if validity_duration <= 0 {
+            return Err(ValidityError::NonPositiveValidityDuration.into());
+        }
+        let max = self.scope.max_validity_duration;
+        if max > 0 && validity_duration > max {
+            return Err(ValidityError::AboveIssuerMaxValidity.into());
+        }
+        Ok(())
+    }
+
+    pub fn check_claim_in_scope(&self, claim_type: ClaimType, value: u16) -> Result<()> {
+        let scope = &self.scope;
+        let type_allowed = scope.claim_types & (1 << claim_type as u8) != 0;
+        let jurisdiction_allowed = claim_type != ClaimType::Country
+            || scope.jurisdictions.is_empty()
+            || scope.jurisdictions.contains(&value);
+        if !type_allowed || !jurisdiction_allowed {
+            return Err(IssuerError::OutOfIssuerScope.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(issuer: Pubkey)]
+pub struct RegisterIssuer<'info> {
+    #[account(init, seeds = [b"issuer", issuer.as_ref()], bump, payer = issuer_admin, space = IssuerRecord::SPACE)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::IssuerAdmin, &issuer_admin.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub issuer_admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeIssuer<'info> {
+    #[account(mut, seeds = [b"issuer", issuer_record.id.as_ref()], bump)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::IssuerAdmin, &issuer_admin.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    pub issuer_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(sub_issuer: Pubkey)]
+pub struct RegisterSubIssuer<'info> {
+    #[account(init, seeds = [b"issuer", sub_issuer.as_ref()], bump, payer = root_issuer, space = IssuerRecord::SPACE)]
+    pub sub_issuer_record: Account<'info, IssuerRecord>,
+    #[account(seeds = [b"issuer", root_issuer_record.id.as_ref()], bump, constraint = root_issuer_record.key == root_issuer.key() @ IssuerError::NotIssuerKey, constraint = root_issuer_record.active @ IssuerError::IssuerNotActive, constraint = root_issuer_record.is_root() @ IssuerError::NotRootIssuer)]
+    pub root_issuer_record: Account<'info, IssuerRecord>,
+    #[account(mut)]
+    pub root_issuer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSubIssuer<'info> {
+    #[account(mut, seeds = [b"issuer", sub_issuer_record.id.as_ref()], bump, constraint = sub_issuer_record.parent == root_issuer_record.id @ IssuerError::NotParentIssuer)]
+    pub sub_issuer_record: Account<'info, IssuerRecord>,
+    #[account(seeds = [b"issuer", root_issuer_record.id.as_ref()], bump, constraint = root_issuer_record.key == root_issuer.key() @ IssuerError::NotIssuerKey)]
+    pub root_issuer_record: Account<'info, IssuerRecord>,
+    pub root_issuer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetIssuerMaxValidity<'info> {
+    #[account(mut, seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.is_root() @ IssuerError::NotRootIssuer)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::IssuerAdmin, &issuer_admin.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    pub issuer_admin: Signer<'info>,
+}
+
+// Both the current and the new key sign, so that a key cannot be rotated to one the issuer does not control
+#[derive(Accounts)]
+pub struct RotateIssuerKey<'info> {
+    #[account(mut, seeds = [b"issuer", issuer_record.id.as_ref()], bump, constraint = issuer_record.key == issuer.key() @ IssuerError::NotIssuerKey)]
+    pub issuer_record: Account<'info, IssuerRecord>,
+    pub issuer: Signer<'info>,
+    pub new_key: Signer<'info>,
+}
+
+#[event]
+pub struct IssuerRegistered {
+    pub issuer: Pubkey,
+    pub issuer_admin: Pubkey,
+}
+
+#[event]
+pub struct IssuerRevoked {
+    pub issuer: Pubkey,
+    pub issuer_admin: Pubkey,
+}
+
+#[event]
+pub struct SubIssuerRegistered {
+    pub sub_issuer: Pubkey,
+    pub root_issuer: Pubkey,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct SubIssuerRevoked {
+    pub sub_issuer: Pubkey,
+    pub root_issuer: Pubkey,
+}
+
+#[event]
+pub struct IssuerMaxValidityUpdated {
+    pub issuer: Pubkey,
+    pub max_validity_duration: i64,
+}
+
+#[event]
+pub struct IssuerKeyRotated {
+    pub issuer: Pubkey,
+    pub previous_key: Pubkey,
+    pub new_key: Pubkey,
+}
+
+#[error_code]
+pub enum IssuerError {
+    #[msg("Issuer is not active")]
+    IssuerNotActive,
+    #[msg("Issuer expired")]
+    IssuerExpired,
+    #[msg("Only a root issuer can delegate to sub-issuers")]
+    NotRootIssuer,
+    #[msg("Not the root issuer of this sub-issuer")]
+    NotParentIssuer,
+    #[msg("The root issuer of this sub-issuer is not active")]
+    ParentIssuerNotActive,
+    #[msg("Out of the scope of the issuer")]
+    OutOfIssuerScope,
+    #[msg("Too many jurisdictions in the scope")]
+    TooManyScopeJurisdictions,
+    #[msg("Not the current key of the issuer")]
+    NotIssuerKey,
+    #[msg("This key was already used by the issuer")]
+    IssuerKeyAlreadyUsed,
+}
+
+pub fn _register_issuer(ctx: Context<RegisterIssuer>, issuer: Pubkey) -> Result<()> {
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    let issuer_record = &mut ctx.accounts.issuer_record;
+    issuer_record.id = issuer;
+    issuer_record.parent = Pubkey::default();
+    issuer_record.key = issuer;
+    issuer_record.active = true;
+    issuer_record.registered_at = current_timestamp;
+    issuer_record.last_modified = current_timestamp;
+    issuer_record.expires_at = i64::MAX;
+    issuer_record.scope = IssuerScope::all();
+    issuer_record.previous_keys = vec![];
+
+    emit!(IssuerRegistered {
+        issuer,
+        issuer_admin: ctx.accounts.issuer_admin.key(),
+    });
+    Ok(())
+}
+
+pub fn _revoke_issuer(ctx: Context<RevokeIssuer>) -> Result<()> {
+    let issuer_record = &mut ctx.accounts.issuer_record;
+    if !issuer_record.active {
+        return Err(IssuerError::IssuerNotActive.into());
+    }
+    issuer_record.active = false;
+    issuer_record.last_modified = Clock::get()?.unix_timestamp;
+
+    emit!(IssuerRevoked {
+        issuer: issuer_record.id,
+        issuer_admin: ctx.accounts.issuer_admin.key(),
+    });
+    Ok(())
+}
+
+pub fn _register_sub_issuer(
+    ctx: Context<RegisterSubIssuer>,
+    sub_issuer: Pubkey,
+    scope: IssuerScope,
+    expires_at: i64,
+) -> Result<()> {
+    if scope.jurisdictions.len() > MAX_SCOPE_JURISDICTIONS {
+        return Err(IssuerError::TooManyScopeJurisdictions.into());
+    }
+    if scope.max_validity_duration < 0 {
+        return Err(ValidityError::InvalidValidityBounds.into());
+    }
+    // A sub-issuer can't attest for longer than its root
+    let root_max = ctx.accounts.root_issuer_record.scope.max_validity_duration;
+    if root_max > 0 && (scope.max_validity_duration == 0 || scope.max_validity_duration > root_max) {
+        return Err(ValidityError::AboveIssuerMaxValidity.into());
+    }
+    let current_timestamp = Clock::get()?.unix_timestamp;
+    if expires_at <= current_timestamp {
+        return Err(IssuerError::IssuerExpired.into());
+    }
+    let sub_issuer_record = &mut ctx.accounts.sub_issuer_record;
+    sub_issuer_record.id = sub_issuer;
+    sub_issuer_record.parent = ctx.accounts.root_issuer_record.id;
+    sub_issuer_record.key = sub_issuer;
+    sub_issuer_record.active = true;
+    sub_issuer_record.registered_at = current_timestamp;
+    sub_issuer_record.last_modified = current_timestamp;
+    sub_issuer_record.expires_at = expires_at;
+    sub_issuer_record.scope = scope;
+    sub_issuer_record.previous_keys = vec![];
+
+    emit!(SubIssuerRegistered {
+        sub_issuer,
+        root_issuer: ctx.accounts.root_issuer_record.id,
+        expires_at,
+    });
+    Ok(())
+}
+
+pub fn _revoke_sub_issuer(ctx: Context<RevokeSubIssuer>) -> Result<()> {
+    let sub_issuer_record = &mut ctx.accounts.sub_issuer_record;
+    if !sub_issuer_record.active {
+        return Err(IssuerError::IssuerNotActive.into());
+    }
+    sub_issuer_record.active = false;
+    sub_issuer_record.last_modified = Clock::get()?.unix_timestamp;
+
+    emit!(SubIssuerRevoked {
+        sub_issuer: sub_issuer_record.id,
+        root_issuer: ctx.accounts.root_issuer_record.id,
+    });
+    Ok(())
+}
+
+// Only for root issuers, the limit of a sub-issuer is set by its root in its scope
+pub fn _set_issuer_max_validity(ctx: Context<SetIssuerMaxValidity>, max_validity_duration: i64) -> Result<()> {
+    if max_validity_duration < 0 {
+        return Err(ValidityError::InvalidValidityBounds.into());
+    }
+    let issuer_record = &mut ctx.accounts.issuer_record;
+    issuer_record.scope.max_validity_duration = max_validity_duration;
+    issuer_record.last_modified = Clock::get()?.unix_timestamp;
+
+    emit!(IssuerMaxValidityUpdated {
+        issuer: issuer_record.id,
+        max_validity_duration,
+    });
+    Ok(())
+}
+
+pub fn _rotate_issuer_key(ctx: Context<RotateIssuerKey>) -> Result<()> {
+    let new_key = ctx.accounts.new_key.key();
+    let issuer_record = &mut ctx.accounts.issuer_record;
+    if new_key == issuer_record.key || issuer_record.previous_keys.contains(&new_key) {
+        return Err(IssuerError::IssuerKeyAlreadyUsed.into());
+    }
+    let previous_key = issuer_record.key;
+    if issuer_record.previous_keys.len() == MAX_ISSUER_KEY_HISTORY {
+        issuer_record.previous_keys.remove(0);
+    }
+    issuer_record.previous_keys.push(previous_key);
+    issuer_record.key = new_key;
+    issuer_record.last_modified = Clock::get()?.unix_timestamp;
+
+    emit!(IssuerKeyRotated {
+        issuer: issuer_record.id,
+        previous_key,
+        new_key,
+    });
+    Ok(())
+}
+
+// The issuer record of the first issuer of an idendity, and its root, are resolved by the transfer hook and may not exist
+pub fn load_issuer_record(account: &AccountInfo) -> Result<Option<IssuerRecord>> {
+    if account.owner != &crate::ID || account.data_is_empty() {
+        return Ok(None);
+    }
+    let data = account.try_borrow_data()?;
+    Ok(Some(IssuerRecord::try_deserialize(&mut &data[..])?))
+}
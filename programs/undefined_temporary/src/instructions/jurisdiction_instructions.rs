@@ -0,0 +1,212 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::{ClaimType, ConfigError, ProgramConfig, Role, MAX_JURISDICTIONS};
+
+// Per mint jurisdiction rules, read by the transfer hook with the country claims of the sender and the receiver.
+// The bit i of the bitsets refers to the country at index i of `countries`, so countries can be added without redeploying.
+#[account]
+pub struct JurisdictionMatrix {
+    pub block_unlisted: bool,    // 1 - countries not listed (or idendities without a country claim) can't transfer
+    pub countries: Vec<u16>,     // 4 + 2 * MAX_JURISDICTIONS - ISO 3166-1 numeric codes
+    pub sanctioned: u64,         // 8 - blocked as sender and as receiver
+    pub blocked_pairs: Vec<u64>, // 4 + 8 * MAX_JURISDICTIONS - row i, bit j: transfers from i to j are blocked
+}
+
+impl JurisdictionMatrix {
+    pub const SPACE: usize = 8 + 1 + 4 + 2 * MAX_JURISDICTIONS + 8 + 4 + 8 * MAX_JURISDICTIONS;
+
+    // No country listed: every transfer is allowed
+    pub fn empty() -> Self {
+        JurisdictionMatrix {
+            block_unlisted: false,
+            countries: vec![],
+            sanctioned: 0,
+            blocked_pairs: vec![],
+        }
+    }
+
+    pub fn index_of(&self, country: u16) -> Option<usize> {
+        self.countries.iter().position(|c| *c == country)
+    }
+
+    // Every country claim of each party is checked: a sanctioned or blocked country can't be hidden behind another one
+    pub fn check_transfer(&self, sender_countries: &[u16], receiver_countries: &[u16]) -> Result<()> {
+        let sender: Vec<Option<usize>> = sender_countries.iter().map(|country| self.index_of(*country)).collect();
+        let receiver: Vec<Option<usize>> = receiver_countries.iter().map(|country| self.index_of(*country)).collect();
+
+        if self.block_unlisted {
+            let all_listed = |indexes: &[Option<usize>]| !indexes.is_empty() && indexes.iter().all(Option::is_some);
+            if !all_listed(&sender) || !all_listed(&receiver) {
+                return Err(JurisdictionError::JurisdictionNotAllowed.into());
+            }
+        }
+        let sender: Vec<usize> = sender.into_iter().flatten().collect();
+        let receiver: Vec<usize> = receiver.into_iter().flatten().collect();
+        for index in sender.iter().chain(receiver.iter()) {
+            if self.sanctioned & (1 << index) != 0 {
+                return Err(JurisdictionError::SanctionedJurisdiction.into());
+            }
+        }
+        for from in sender.iter() {
+            for to in receiver.iter() {
+                if self.blocked_pairs[*from] & (1 << to) != 0 {
+                    return Err(JurisdictionError::JurisdictionPairBlocked.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn index_of_listed(&self, country: u16) -> Result<usize> {
+        self.index_of(country)
+            .ok_or(JurisdictionError::JurisdictionNotListed.into())
+    }
+}
+
+// The matrix is created with the mint, a mint created by an older version of the program gets it afterwards
+#[derive(Accounts)]
+pub struct InitializeJurisdictionMatrix<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.super_admin == payer.key() @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(init, seeds = [b"jurisdiction", mint.key().as_ref()], bump, payer = payer, space = JurisdictionMatrix::SPACE)]
+    pub jurisdiction_matrix: Account<'info, JurisdictionMatrix>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateJurisdictionMatrix<'info> {
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Compliance, &compliance.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"jurisdiction", mint.key().as_ref()], bump)]
+    pub jurisdiction_matrix: Account<'info, JurisdictionMatrix>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub compliance: Signer<'info>,
+}
+
+#[event]
+pub struct JurisdictionAdded {
+    pub mint: Pubkey,
+    pub country: u16,
+}
+
+#[event]
+pub struct JurisdictionSanctionUpdated {
+    pub mint: Pubkey,
+    pub country: u16,
+    pub sanctioned: bool,
+}
+
+#[event]
+pub struct JurisdictionPairUpdated {
+    pub mint: Pubkey,
+    pub from: u16,
+    pub to: u16,
+    pub blocked: bool,
+}
+
+#[event]
+pub struct BlockUnlistedJurisdictionsUpdated {
+    pub mint: Pubkey,
+    pub block_unlisted: bool,
+}
+
+#[error_code]
+pub enum JurisdictionError {
+    #[msg("Too many jurisdictions")]
+    TooManyJurisdictions,
+    #[msg("Jurisdiction already listed")]
+    JurisdictionAlreadyListed,
+    #[msg("Jurisdiction not listed")]
+    JurisdictionNotListed,
+    #[msg("The sender or the receiver is in a sanctioned jurisdiction")]
+    SanctionedJurisdiction,
+    #[msg("Transfers between these jurisdictions are blocked")]
+    JurisdictionPairBlocked,
+    #[msg("The sender or the receiver is not in an allowed jurisdiction")]
+    JurisdictionNotAllowed,
+}
+
+pub fn _initialize_jurisdiction_matrix(ctx: Context<InitializeJurisdictionMatrix>) -> Result<()> {
+    ctx.accounts.jurisdiction_matrix.set_inner(JurisdictionMatrix::empty());
+    Ok(())
+}
+
+pub fn _add_jurisdiction(ctx: Context<UpdateJurisdictionMatrix>, country: u16) -> Result<()> {
+    ClaimType::Country.check_value(country)?;
+    let matrix = &mut ctx.accounts.jurisdiction_matrix;
+    if matrix.index_of(country).is_some() {
+        return Err(JurisdictionError::JurisdictionAlreadyListed.into());
+    }
+    if matrix.countries.len() >= MAX_JURISDICTIONS {
+        return Err(JurisdictionError::TooManyJurisdictions.into());
+    }
+    matrix.countries.push(country);
+    matrix.blocked_pairs.push(0);
+
+    emit!(JurisdictionAdded {
+        mint: ctx.accounts.mint.key(),
+        country,
+    });
+    Ok(())
+}
+
+pub fn _set_jurisdiction_sanctioned(
+    ctx: Context<UpdateJurisdictionMatrix>,
+    country: u16,
+    sanctioned: bool,
+) -> Result<()> {
+    let matrix = &mut ctx.accounts.jurisdiction_matrix;
+    let index = matrix.index_of_listed(country)?;
+    if sanctioned {
+        matrix.sanctioned |= 1 << index;
+    } else {
+        matrix.sanctioned &= !(1 << index);
+    }
+
+    emit!(JurisdictionSanctionUpdated {
+        mint: ctx.accounts.mint.key(),
+        country,
+        sanctioned,
+    });
+    Ok(())
+}
+
+pub fn _set_jurisdiction_pair(
+    ctx: Context<UpdateJurisdictionMatrix>,
+    from: u16,
+    to: u16,
+    blocked: bool,
+) -> Result<()> {
+    let matrix = &mut ctx.accounts.jurisdiction_matrix;
+    let from_index = matrix.index_of_listed(from)?;
+    let to_index = matrix.index_of_listed(to)?;
+    if blocked {
+        matrix.blocked_pairs[from_index] |= 1 << to_index;
+    } else {
+        matrix.blocked_pairs[from_index] &= !(1 << to_index);
+    }
+
+    emit!(JurisdictionPairUpdated {
+        mint: ctx.accounts.mint.key(),
+        from,
+        to,
+        blocked,
+    });
+    Ok(())
+}
+
+pub fn _set_block_unlisted_jurisdictions(ctx: Context<UpdateJurisdictionMatrix>, block_unlisted: bool) -> Result<()> {
+    ctx.accounts.jurisdiction_matrix.block_unlisted = block_unlisted;
+
+    emit!(BlockUnlistedJurisdictionsUpdated {
+        mint: ctx.accounts.mint.key(),
+        block_unlisted,
+    });
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{self, allocate, assign, transfer, Allocate, Assign, Transfer},
+};
+
+// Part of the balance of a token account that can't be transferred, created by the minter with the minted tokens (see mint_to).
+// Nothing is released before the cliff, then the amount is released linearly until the end.
+// A lockup until a date has the same start, cliff and end.
+// Only the minter that created it can extend it, it can be closed once released and a new one created by a later mint.
+#[account]
+pub struct Lockup {
+    pub token_account: Pubkey, // 32
+    pub amount: u64,           // 8
+    pub start: i64,            // 8
+    pub cliff: i64,            // 8
+    pub end: i64,              // 8
+    pub authority: Pubkey,     // 32
+    pub payer: Pubkey,         // 32 - gets the rent back when the lockup is closed or moved
+}
+
+impl Lockup {
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 32 + 32;
+
+    pub fn locked_amount(&self, now: i64) -> u64 {
+        if now < self.cliff {
+            return self.amount;
+        }
+        if now >= self.end {
+            return 0;
+        }
+        let remaining = (self.end - now) as u128;
+        let duration = (self.end - self.start) as u128;
+        (self.amount as u128 * remaining / duration) as u64
+    }
+}
+
+// Schedule of the tokens locked by mint_to
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LockupSchedule {
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+}
+
+// Anyone can close a released lockup, the rent goes back to its payer
+#[derive(Accounts)]
+pub struct CloseLockup<'info> {
+    #[account(mut, seeds = [b"lockup", lockup.token_account.as_ref()], bump, has_one = payer @ LockupError::WrongLockupPayer, close = payer)]
+    pub lockup: Account<'info, Lockup>,
+    /// CHECK: Payer of the rent of the lockup
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[event]
+pub struct LockupCreated {
+    pub token_account: Pubkey,
+    pub amount: u64,
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct LockupExtended {
+    pub token_account: Pubkey,
+    pub amount: u64,
+    pub start: i64,
+    pub cliff: i64,
+    pub end: i64,
+}
+
+#[event]
+pub struct LockupClosed {
+    pub token_account: Pubkey,
+}
+
+#[event]
+pub struct LockupMoved {
+    pub from: Pubkey,
+    pub to: Pubkey,
+}
+
+#[error_code]
+pub enum LockupError {
+    #[msg("Invalid schedule: start <= cliff <= end is required")]
+    InvalidSchedule,
+    #[msg("The transfer would use locked tokens")]
+    TokensLocked,
+    #[msg("The recovered token account already has a lockup")]
+    LockupAlreadyExists,
+    #[msg("Only the authority of the lockup can extend it")]
+    NotLockupAuthority,
+    #[msg("The extended lockup would release the locked tokens earlier")]
+    LockupShortened,
+    #[msg("The lockup still locks tokens")]
+    LockupNotReleased,
+    #[msg("The payer of the lockup is missing or wrong")]
+    WrongLockupPayer,
+}
+
+pub fn _close_lockup(ctx: Context<CloseLockup>) -> Result<()> {
+    if ctx.accounts.lockup.locked_amount(Clock::get()?.unix_timestamp) > 0 {
+        return Err(LockupError::LockupNotReleased.into());
+    }
+
+    emit!(LockupClosed {
+        token_account: ctx.accounts.lockup.token_account,
+    });
+    Ok(())
+}
+
+// Creates the account of a lockup, also when lamports were already sent to its address
+fn create_lockup_account<'info>(
+    lockup: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_account: &Pubkey,
+    bump: u8,
+) -> Result<()> {
+    let signer: &[&[&[u8]]] = &[&[b"lockup", token_account.as_ref(), &[bump]]];
+    let lamports = Rent::get()?.minimum_balance(Lockup::SPACE).saturating_sub(lockup.lamports());
+    if lamports > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: lockup.clone(),
+                },
+            ),
+            lamports,
+        )?;
+    }
+    allocate(
+        CpiContext::new(system_program.clone(), Allocate { account_to_allocate: lockup.clone() }).with_signer(signer),
+        Lockup::SPACE as u64,
+    )?;
+    assign(
+        CpiContext::new(system_program.clone(), Assign { account_to_assign: lockup.clone() }).with_signer(signer),
+        &crate::ID,
+    )
+}
+
+// Locks minted tokens: creates the lockup of the token account or extends it with the minted amount.
+// The tokens still locked by the lockup stay locked under the new schedule, which must not release them earlier:
+// it starts now or later and its cliff and end are not before the current ones.
+pub fn lock_minted_tokens<'info>(
+    lockup: &AccountInfo<'info>,
+    token_account: Pubkey,
+    amount: u64,
+    schedule: &LockupSchedule,
+    minter: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    bump: u8,
+) -> Result<()> {
+    if schedule.start > schedule.cliff || schedule.cliff > schedule.end {
+        return Err(LockupError::InvalidSchedule.into());
+    }
+    let now = Clock::get()?.unix_timestamp;
+
+    let updated = match load_lockup(lockup)? {
+        Some(current) => {
+            if current.authority != minter.key() {
+                return Err(LockupError::NotLockupAuthority.into());
+            }
+            if schedule.start < now || schedule.cliff < current.cliff || schedule.end < current.end {
+                return Err(LockupError::LockupShortened.into());
+            }
+            let amount = current
+                .locked_amount(now)
+                .checked_add(amount)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+            emit!(LockupExtended {
+                token_account,
+                amount,
+                start: schedule.start,
+                cliff: schedule.cliff,
+                end: schedule.end,
+            });
+            Lockup {
+                amount,
+                start: schedule.start,
+                cliff: schedule.cliff,
+                end: schedule.end,
+                ..current
+            }
+        }
+        None => {
+            create_lockup_account(lockup, minter, system_program, &token_account, bump)?;
+            emit!(LockupCreated {
+                token_account,
+                amount,
+                start: schedule.start,
+                cliff: schedule.cliff,
+                end: schedule.end,
+                authority: minter.key(),
+            });
+            Lockup {
+                token_account,
+                amount,
+                start: schedule.start,
+                cliff: schedule.cliff,
+                end: schedule.end,
+                authority: minter.key(),
+                payer: minter.key(),
+            }
+        }
+    };
+    updated.try_serialize(&mut &mut lockup.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+// Lockups only exist for the token accounts that received locked tokens
+pub fn load_lockup(account: &AccountInfo) -> Result<Option<Lockup>> {
+    if account.owner != &crate::ID || account.data_is_empty() {
+        return Ok(None);
+    }
+    let data = account.try_borrow_data()?;
+    Ok(Some(Lockup::try_deserialize(&mut &data[..])?))
+}
+
+pub fn check_not_locked(lockup: &AccountInfo, balance_after: u64, now: i64) -> Result<()> {
+    if let Some(lockup) = load_lockup(lockup)? {
+        if balance_after < lockup.locked_amount(now) {
+            return Err(LockupError::TokensLocked.into());
+        }
+    }
+    Ok(())
+}
+
+// Moves the lockup of a recovered token account to the new token account. The payer pays the rent of the new lockup
+// and becomes its payer, the payer of the old lockup gets its rent back.
+pub fn move_lockup<'info>(
+    lockup: &AccountInfo<'info>,
+    new_lockup: &AccountInfo<'info>,
+    new_token_account: Pubkey,
+    lockup_payer: Option<&AccountInfo<'info>>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    new_lockup_bump: u8,
+) -> Result<()> {
+    let mut moved = match load_lockup(lockup)? {
+        Some(moved) => moved,
+        None => return Ok(()),
+    };
+    let lockup_payer = match lockup_payer {
+        Some(lockup_payer) if lockup_payer.key() == moved.payer => lockup_payer,
+        _ => return Err(LockupError::WrongLockupPayer.into()),
+    };
+    if !new_lockup.data_is_empty() {
+        return Err(LockupError::LockupAlreadyExists.into());
+    }
+
+    create_lockup_account(new_lockup, payer, system_program, &new_token_account, new_lockup_bump)?;
+    let from = moved.token_account;
+    moved.token_account = new_token_account;
+    moved.payer = payer.key();
+    moved.try_serialize(&mut &mut new_lockup.try_borrow_mut_data()?[..])?;
+
+    let refund = lockup.lamports();
+    **lockup.try_borrow_mut_lamports()? = 0;
+    **lockup_payer.try_borrow_mut_lamports()? += refund;
+    lockup.assign(&system_program::ID);
+    lockup.realloc(0, false)?;
+
+    emit!(LockupMoved {
+        from,
+        to: new_token_account,
+    });
+    Ok(())
+}
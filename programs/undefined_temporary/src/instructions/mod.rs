@@ -18,3 +18,51 @@ pub use wrapper::*;
 
 pub mod two_auth_instructions;
 pub use two_auth_instructions::*;
+
+pub mod compliance_instructions;
+pub use compliance_instructions::*;
+
+pub mod config_instructions;
+pub use config_instructions::*;
+
+pub mod issuer_registry_instructions;
+pub use issuer_registry_instructions::*;
+
+pub mod pause_instructions;
+pub use pause_instructions::*;
+
+pub mod consent_instructions;
+pub use consent_instructions::*;
+
+pub mod claim_instructions;
+pub use claim_instructions::*;
+
+pub mod jurisdiction_instructions;
+pub use jurisdiction_instructions::*;
+
+pub mod commitment_instructions;
+pub use commitment_instructions::*;
+
+pub mod attestation_instructions;
+pub use attestation_instructions::*;
+
+pub mod prune_instructions;
+pub use prune_instructions::*;
+
+pub mod identity_link_instructions;
+pub use identity_link_instructions::*;
+
+pub mod institution_instructions;
+pub use institution_instructions::*;
+
+pub mod deny_list_instructions;
+pub use deny_list_instructions::*;
+
+pub mod balance_cap_instructions;
+pub use balance_cap_instructions::*;
+
+pub mod holder_count_instructions;
+pub use holder_count_instructions::*;
+
+pub mod lockup_instructions;
+pub use lockup_instructions::*;
@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::{ConfigError, MintConfig, ProgramConfig, Role, MAX_PAUSE_EXEMPTIONS};
+
+#[derive(Accounts)]
+pub struct SetMintPaused<'info> {
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::Pauser, &pauser.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub pauser: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGlobalPaused<'info> {
+    #[account(mut, seeds = [b"config"], bump, constraint = config.has_role(Role::Pauser, &pauser.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    pub pauser: Signer<'info>,
+}
+
+// The exemptions are managed by the super admin: a pauser should not be able to exempt itself
+#[derive(Accounts)]
+pub struct UpdatePauseExemption<'info> {
+    #[account(seeds = [b"config"], bump, has_one = super_admin @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub super_admin: Signer<'info>,
+}
+
+#[event]
+pub struct Paused {
+    pub mint: Option<Pubkey>, // None for the global pause
+    pub pauser: Pubkey,
+}
+
+#[event]
+pub struct Unpaused {
+    pub mint: Option<Pubkey>, // None for the global pause
+    pub pauser: Pubkey,
+}
+
+#[error_code]
+pub enum PauseError {
+    #[msg("Paused")]
+    Paused,
+    #[msg("Already paused")]
+    AlreadyPaused,
+    #[msg("Not paused")]
+    NotPaused,
+    #[msg("Too many pause exemptions")]
+    TooManyExemptions,
+    #[msg("Account already exempted")]
+    ExemptionAlreadyExists,
+    #[msg("Account not exempted")]
+    ExemptionNotFound,
+}
+
+pub fn _set_mint_paused(ctx: Context<SetMintPaused>, paused: bool) -> Result<()> {
+    let mint_config = &mut ctx.accounts.mint_config;
+    check_pause_change(mint_config.paused, paused)?;
+    mint_config.paused = paused;
+
+    emit_pause_event(Some(ctx.accounts.mint.key()), ctx.accounts.pauser.key(), paused);
+    Ok(())
+}
+
+pub fn _set_global_paused(ctx: Context<SetGlobalPaused>, paused: bool) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    check_pause_change(config.paused, paused)?;
+    config.paused = paused;
+
+    emit_pause_event(None, ctx.accounts.pauser.key(), paused);
+    Ok(())
+}
+
+pub fn _add_pause_exemption(ctx: Context<UpdatePauseExemption>, token_account: Pubkey) -> Result<()> {
+    let exemptions = &mut ctx.accounts.mint_config.pause_exemptions;
+    if exemptions.contains(&token_account) {
+        return Err(PauseError::ExemptionAlreadyExists.into());
+    }
+    if exemptions.len() >= MAX_PAUSE_EXEMPTIONS {
+        return Err(PauseError::TooManyExemptions.into());
+    }
+    exemptions.push(token_account);
+    Ok(())
+}
+
+pub fn _remove_pause_exemption(ctx: Context<UpdatePauseExemption>, token_account: Pubkey) -> Result<()> {
+    let exemptions = &mut ctx.accounts.mint_config.pause_exemptions;
+    if !exemptions.contains(&token_account) {
+        return Err(PauseError::ExemptionNotFound.into());
+    }
+    exemptions.retain(|exemption| *exemption != token_account);
+    Ok(())
+}
+
+#[inline(always)]
+fn check_pause_change(current: bool, paused: bool) -> Result<()> {
+    if paused && current {
+        return Err(PauseError::AlreadyPaused.into());
+    }
+    if !paused && !current {
+        return Err(PauseError::NotPaused.into());
+    }
+    Ok(())
+}
+
+#[inline(always)]
+fn emit_pause_event(mint: Option<Pubkey>, pauser: Pubkey, paused: bool) {
+    if paused {
+        emit!(Paused { mint, pauser });
+    } else {
+        emit!(Unpaused { mint, pauser });
+    }
+}
+
+/*
+    Returns an error if the mint or the whole program is paused
+*/
+pub fn check_not_paused(config: &ProgramConfig, mint_config: &MintConfig) -> Result<()> {
+    if config.paused || mint_config.paused {
+        return Err(PauseError::Paused.into());
+    }
+    Ok(())
+}
+
+/*
+    Same as check_not_paused but lets through the transfers from or to an exempted token account
+*/
+pub fn check_transfer_not_paused(
+    config: &ProgramConfig,
+    mint_config: &MintConfig,
+    source: &Pubkey,
+    destination: &Pubkey,
+) -> Result<()> {
+    let exemptions = &mint_config.pause_exemptions;
+    if exemptions.contains(source) || exemptions.contains(destination) {
+        return Ok(());
+    }
+    check_not_paused(config, mint_config)
+}
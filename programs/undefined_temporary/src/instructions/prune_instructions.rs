@@ -0,0 +1,137 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::{
+    token_2022,
+    token_interface::{Mint, TokenAccount},
+};
+
+use crate::{load_holdings, IdAccount, Issuer, EXPIRED_REASON_CODE, PRUNE_BOUNTY_BPS};
+
+// Permissionless crank: anyone can mark the expired issuers of an idendity inactive, and close the idendity once all its
+// issuers expired and it holds no token.
+// A revoked or suspended idendity is kept: closing it would drop the decision of the issuer and let it be issued again.
+#[derive(Accounts)]
+pub struct PruneExpiredIdendity<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(mut, seeds = [b"identity", token_account.key().as_ref()], bump)]
+    pub idendity: Account<'info, IdAccount>,
+    /// CHECK: Token account of an idendity issued for a single token account, which may have been closed by its owner,
+    /// or owner or entity key of a person or institution idendity (see idendity_balance). The entity gets the rent of its Institution back.
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+    #[account(seeds = [b"mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: Holdings of a person or institution idendity, may not exist (see load_holdings)
+    #[account(seeds = [b"holdings", mint.key().as_ref(), token_account.key().as_ref()], bump)]
+    pub holdings: UncheckedAccount<'info>,
+    /// CHECK: Institution of an institution idendity, closed with it so that the entity can be issued an idendity again
+    #[account(mut, seeds = [b"institution", token_account.key().as_ref()], bump)]
+    pub institution: UncheckedAccount<'info>,
+    /// CHECK: Only receives the rent, must be the payer of the idendity
+    #[account(mut, address = idendity.payer @ PruneError::NotIdendityPayer)]
+    pub payer: AccountInfo<'info>,
+}
+
+#[event]
+pub struct IssuersExpired {
+    pub idendity: Pubkey,
+    pub expired_issuers: u8,
+}
+
+#[event]
+pub struct IdendityPruned {
+    pub token_account: Pubkey,
+    pub expired_issuers: u8,
+    pub bounty: u64,
+}
+
+#[error_code]
+pub enum PruneError {
+    #[msg("Nothing to prune: no issuer newly expired, and an issuer did not expire, revoked or suspended the idendity, or it holds tokens")]
+    NothingToPrune,
+    #[msg("The rent can only be refunded to the payer of the idendity")]
+    NotIdendityPayer,
+}
+
+pub fn _prune_expired_idendity(ctx: Context<PruneExpiredIdendity>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let idendity = &mut ctx.accounts.idendity;
+
+    let mut newly_expired: u8 = 0;
+    for issuer in idendity.issuers.iter_mut() {
+        if issuer.expires_at < now && issuer.active && !issuer.revoked {
+            issuer.active = false;
+            issuer.reason_code = EXPIRED_REASON_CODE;
+            issuer.last_modified = now;
+            newly_expired += 1;
+        }
+    }
+
+    let all_expired = !idendity.issuers.is_empty() && idendity.issuers.iter().all(|issuer| marked_expired(issuer, now));
+    if !all_expired || idendity_balance(idendity, &ctx.accounts.token_account, &ctx.accounts.holdings)? != 0 {
+        if newly_expired == 0 {
+            return Err(PruneError::NothingToPrune.into());
+        }
+        // The idendity is kept, its expired issuers no longer vouch for it
+        emit!(IssuersExpired {
+            idendity: idendity.key(),
+            expired_issuers: newly_expired,
+        });
+        return Ok(());
+    }
+    let expired_issuers = idendity.issuers.len() as u8;
+
+    // The cranker gets a share of the rent, the rest goes back to the payer
+    let idendity_info = ctx.accounts.idendity.to_account_info();
+    let bounty = idendity_info.lamports() * PRUNE_BOUNTY_BPS / 10_000;
+    **idendity_info.try_borrow_mut_lamports()? -= bounty;
+    **ctx.accounts.cranker.try_borrow_mut_lamports()? += bounty;
+    ctx.accounts.idendity.close(ctx.accounts.payer.to_account_info())?;
+    close_institution(&ctx.accounts.institution, &ctx.accounts.token_account)?;
+
+    emit!(IdendityPruned {
+        token_account: ctx.accounts.token_account.key(),
+        expired_issuers,
+        bounty,
+    });
+    Ok(())
+}
+
+// The entity paid the rent of its Institution (see InitializeInstitutionId)
+fn close_institution<'info>(institution: &AccountInfo<'info>, entity: &AccountInfo<'info>) -> Result<()> {
+    if institution.owner != &crate::ID || institution.data_is_empty() {
+        return Ok(());
+    }
+    let refund = institution.lamports();
+    **institution.try_borrow_mut_lamports()? = 0;
+    **entity.try_borrow_mut_lamports()? += refund;
+    institution.assign(&system_program::ID);
+    institution.realloc(0, false)?;
+    Ok(())
+}
+
+// Marked inactive by the crank, not suspended by the issuer
+fn marked_expired(issuer: &Issuer, now: i64) -> bool {
+    issuer.expires_at < now && !issuer.active && !issuer.revoked && issuer.reason_code == EXPIRED_REASON_CODE
+}
+
+// A person or institution idendity is seeded by a key that is not a token account: the balance of the token accounts
+// it links is followed by its holdings
+fn idendity_balance(idendity: &IdAccount, token_account: &AccountInfo, holdings: &AccountInfo) -> Result<u64> {
+    if idendity.token_account == Pubkey::default() {
+        return Ok(load_holdings(holdings)?.map_or(0, |holdings| holdings.amount));
+    }
+    token_balance(token_account)
+}
+
+// A closed token account holds no token
+pub fn token_balance(token_account: &AccountInfo) -> Result<u64> {
+    if token_account.data_is_empty() {
+        return Ok(0);
+    }
+    if token_account.owner != &token_2022::ID {
+        return Err(ErrorCode::ConstraintOwner.into());
+    }
+    let data = token_account.try_borrow_data()?;
+    Ok(TokenAccount::try_deserialize(&mut &data[..])?.amount)
+}
@@ -5,7 +5,7 @@ use anchor_spl::{
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
-use crate::{IdAccount, IdendityError};
+use crate::{check_linked_holdings, check_not_paused, move_holdings, move_lockup, IdAccount, IdendityError, MintConfig, ProgramConfig};
 
 // LastTx is used to store the last transaction timestamp
 // It will be used by the recovery functions to check if the last transaction was made within the timeframe specified by the user
@@ -49,14 +49,39 @@ pub struct RecoverAccount<'info> {
     pub recovery_authority: Account<'info, RecoveryAuthority>,
     #[account(seeds = [b"last_tx", owner.key().as_ref()], bump)]
     pub last_tx: Account<'info, LastTx>,
-    #[account(mut, seeds = [b"identity", token_account.key().as_ref()], bump, realloc = 80 + 49 * idendity.issuers.len() + 32, realloc::payer = new_owner, realloc::zero= false)]
+    #[account(mut, seeds = [b"identity", token_account.key().as_ref()], bump, realloc = IdAccount::space(idendity.issuers.len(), 1, idendity.claims.len()), realloc::payer = new_owner, realloc::zero= false)]
     pub idendity: Account<'info, IdAccount>,
     /// CHECK: Account to recover
     pub owner: AccountInfo<'info>,
     #[account(mut, seeds = [b"mint"], bump)]
     pub mint: InterfaceAccount<'info, Mint>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
     #[account(mut, token::authority = owner.key())]
     pub token_account: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: Lockup of the recovered token account, may not exist (see move_lockup)
+    #[account(mut, seeds = [b"lockup", token_account.key().as_ref()], bump)]
+    pub lockup: UncheckedAccount<'info>,
+    /// CHECK: Lockup of the new token account, created if the recovered token account has one
+    #[account(mut, seeds = [b"lockup", new_token_account.key().as_ref()], bump)]
+    pub new_lockup: UncheckedAccount<'info>,
+    /// CHECK: Payer of the lockup of the recovered token account, gets its rent back (see move_lockup)
+    #[account(mut)]
+    pub lockup_payer: Option<UncheckedAccount<'info>>,
+    /// CHECK: Link of the recovered token account, may not exist (see check_linked_holdings)
+    #[account(seeds = [b"identity_link", token_account.key().as_ref()], bump)]
+    pub link: UncheckedAccount<'info>,
+    /// CHECK: Holdings of the subject of link, may not exist
+    #[account(mut)]
+    pub holdings: UncheckedAccount<'info>,
+    /// CHECK: Link of the new token account, may not exist (see check_linked_holdings)
+    #[account(seeds = [b"identity_link", new_token_account.key().as_ref()], bump)]
+    pub new_link: UncheckedAccount<'info>,
+    /// CHECK: Holdings of the subject of new_link, may not exist
+    #[account(mut)]
+    pub new_holdings: UncheckedAccount<'info>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
@@ -85,6 +110,8 @@ pub fn _initialize_recovery(
 }
 
 pub fn _recovering_account(ctx: Context<RecoverAccount>) -> Result<()> {
+    check_not_paused(&ctx.accounts.config, &ctx.accounts.mint_config)?;
+
     let recovery_authority = &ctx.accounts.recovery_authority;
 
     let signers: Vec<_> = ctx
@@ -126,6 +153,29 @@ pub fn _recovering_account(ctx: Context<RecoverAccount>) -> Result<()> {
     burn_tokens(&ctx, seeds, amount)?;
     mint_tokens(&ctx, seeds, amount)?;
 
+    let new_balance_before = ctx.accounts.new_token_account.amount;
+    let token_account = ctx.accounts.token_account.key();
+    let new_token_account = ctx.accounts.new_token_account.key();
+    let holder_count = &mut ctx.accounts.mint_config.holder_count;
+    holder_count.record(&token_account, amount, 0)?;
+    holder_count.record(&new_token_account, new_balance_before, new_balance_before.saturating_add(amount))?;
+
+    let mint = ctx.accounts.mint.key();
+    check_linked_holdings(&ctx.accounts.link, &ctx.accounts.holdings, &mint)?;
+    check_linked_holdings(&ctx.accounts.new_link, &ctx.accounts.new_holdings, &mint)?;
+    move_holdings(&ctx.accounts.holdings, &ctx.accounts.new_holdings, amount)?;
+
+    // The locked tokens stay locked on the new token account
+    move_lockup(
+        &ctx.accounts.lockup,
+        &ctx.accounts.new_lockup,
+        ctx.accounts.new_token_account.key(),
+        ctx.accounts.lockup_payer.as_ref().map(|lockup_payer| lockup_payer.as_ref()),
+        &ctx.accounts.new_owner,
+        &ctx.accounts.system_program,
+        ctx.bumps.new_lockup,
+    )?;
+
     let token_account = &ctx.accounts.token_account;
 
     if token_account.close_authority == Some(ctx.accounts.mint.key()).into() {
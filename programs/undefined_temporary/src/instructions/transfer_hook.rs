@@ -1,9 +1,15 @@
 use anchor_lang::{
     prelude::*,
-    system_program::{create_account, CreateAccount},
+    system_program::{create_account, transfer, CreateAccount, Transfer},
 };
 use anchor_spl::{
     associated_token::AssociatedToken,
+    token_2022::spl_token_2022::{
+        extension::{
+            transfer_hook::TransferHookAccount, BaseStateWithExtensions, StateWithExtensions,
+        },
+        state::Account as Token2022Account,
+    },
     token_interface::{Mint, TokenAccount, TokenInterface},
 };
 use spl_tlv_account_resolution::{
@@ -12,8 +18,11 @@ use spl_tlv_account_resolution::{
 use spl_transfer_hook_interface::instruction::ExecuteInstruction;
 
 use crate::{
-    apply_two_auth_functions, IdAccount, IdendityError, LastTx, TransactionAproval, TwoAuthError,
-    TwoAuthParameters,
+    apply_two_auth_functions, check_institution_link, check_linked, check_not_denied,
+    check_not_locked, check_required_claims, check_transfer_not_paused, load_issuer_record,
+    load_proven_claims, move_holdings, valid_claim_values, ClaimParty, ClaimType, ConfigError,
+    IdAccount, IdendityError, IdentityLink, IssuerError, JurisdictionMatrix, LastTx, MintConfig,
+    ProgramConfig, TransactionAproval, TwoAuthError, TwoAuthParameters, EXTRA_ACCOUNT_METAS_VERSION,
 };
 
 #[derive(Accounts)]
@@ -21,6 +30,8 @@ use crate::{
 pub struct InitializeExtraAccountMetaList<'info> {
     #[account(mut)]
     payer: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.super_admin == payer.key() @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
     /// CHECK: ExtraAccountMetaList Account, must use these seeds
     #[account(
         mut,
@@ -31,11 +42,32 @@ pub struct InitializeExtraAccountMetaList<'info> {
     // #[account(seeds = [b"mint"],bump)]
     /// CHECK:
     pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"config"], bump, constraint = config.super_admin == payer.key() @ ConfigError::NotSuperAdmin)]
+    pub config: Account<'info, ProgramConfig>,
+    /// CHECK: ExtraAccountMetaList Account, must use these seeds
+    #[account(
+        mut,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct TransferHook<'info> {
     #[account(
@@ -56,44 +88,132 @@ pub struct TransferHook<'info> {
     bump
     )]
     pub extra_account_meta_list: UncheckedAccount<'info>, // 4
-    #[account(seeds = [b"identity", source_token.key().as_ref()], bump)]
-    pub idendity_sender: Account<'info, IdAccount>, // 5
-    #[account(seeds = [b"identity", destination_token.key().as_ref()], bump)]
-    pub idendity_receiver: Account<'info, IdAccount>, // 6
-    #[account(seeds = [b"last_tx", owner.key().as_ref()], bump)]
-    pub last_tx: Account<'info, LastTx>, // 7
+    #[account(seeds = [b"identity_link", source_token.key().as_ref()], bump)]
+    pub link_sender: Account<'info, IdentityLink>, // 5
+    #[account(seeds = [b"identity_link", destination_token.key().as_ref()], bump)]
+    pub link_receiver: Account<'info, IdentityLink>, // 6
+    #[account(seeds = [b"identity", link_sender.subject.as_ref()], bump)]
+    pub idendity_sender: Account<'info, IdAccount>, // 7
+    #[account(seeds = [b"identity", link_receiver.subject.as_ref()], bump)]
+    pub idendity_receiver: Account<'info, IdAccount>, // 8
+    #[account(mut, seeds = [b"last_tx", owner.key().as_ref()], bump)]
+    pub last_tx: Account<'info, LastTx>, // 9
     #[account(seeds=[b"two_auth", source_token.key().as_ref()], bump)]
-    pub two_auth: Account<'info, TwoAuthParameters>, // 8
-    #[account(seeds=[b"transaction_approval", owner.key().as_ref()], bump)]
-    pub transaction_approval: Account<'info, TransactionAproval>, // 9
+    pub two_auth: Account<'info, TwoAuthParameters>, // 10
+    #[account(mut, seeds=[b"transaction_approval", owner.key().as_ref()], bump)]
+    pub transaction_approval: Account<'info, TransactionAproval>, // 11
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, ProgramConfig>, // 12
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>, // 13
+    #[account(seeds = [b"jurisdiction", mint.key().as_ref()], bump)]
+    pub jurisdiction_matrix: Account<'info, JurisdictionMatrix>, // 14
+    /// CHECK: Claims proven by the sender, may not exist (see load_proven_claims)
+    #[account(seeds = [b"claim_proof", source_token.key().as_ref()], bump)]
+    pub claim_proof_sender: UncheckedAccount<'info>, // 15
+    /// CHECK: Claims proven by the receiver, may not exist (see load_proven_claims)
+    #[account(seeds = [b"claim_proof", destination_token.key().as_ref()], bump)]
+    pub claim_proof_receiver: UncheckedAccount<'info>, // 16
+    /// CHECK: Issuer record of the first issuer of the sender idendity (see check_issuer_chain)
+    pub issuer_record_sender: UncheckedAccount<'info>, // 17
+    /// CHECK: Root issuer record of issuer_record_sender, does not exist for a root issuer
+    pub root_issuer_record_sender: UncheckedAccount<'info>, // 18
+    /// CHECK: Issuer record of the first issuer of the receiver idendity (see check_issuer_chain)
+    pub issuer_record_receiver: UncheckedAccount<'info>, // 19
+    /// CHECK: Root issuer record of issuer_record_receiver, does not exist for a root issuer
+    pub root_issuer_record_receiver: UncheckedAccount<'info>, // 20
+    /// CHECK: Institution of the sender idendity, does not exist for other idendities (see check_institution_link)
+    pub institution_sender: UncheckedAccount<'info>, // 21
+    /// CHECK: Institution of the receiver idendity, does not exist for other idendities (see check_institution_link)
+    pub institution_receiver: UncheckedAccount<'info>, // 22
+    /// CHECK: Sorted denied addresses, searched in place (see check_not_denied)
+    #[account(seeds = [b"deny_list"], bump, owner = crate::ID)]
+    pub deny_list: UncheckedAccount<'info>, // 23
+    /// CHECK: Holdings of the sender idendity, only exists for person and institution idendities (see load_holdings)
+    #[account(mut)]
+    pub holdings_sender: UncheckedAccount<'info>, // 24
+    /// CHECK: Holdings of the receiver idendity, only exists for person and institution idendities (see load_holdings)
+    #[account(mut)]
+    pub holdings_receiver: UncheckedAccount<'info>, // 25
+    /// CHECK: Lockup of the source token account, may not exist (see check_not_locked)
+    #[account(seeds = [b"lockup", source_token.key().as_ref()], bump)]
+    pub lockup: UncheckedAccount<'info>, // 26
                                              // CHECK: destination token account owner
                                              // pub destination_owner: UncheckedAccount<'info>, // 8
 }
 
-pub fn _initialize_extra_account_meta_list(
-    ctx: Context<InitializeExtraAccountMetaList>,
-) -> Result<()> {
-    // List of issuers
+#[event]
+pub struct ExtraAccountMetaListUpdated {
+    pub mint: Pubkey,
+    pub version: u16,
+}
+
+#[event]
+pub struct TransactionApprovalConsumed {
+    pub owner: Pubkey,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
 
-    let account_metas = vec![
-        // Sender Idendity
+#[error_code]
+pub enum TransferHookError {
+    #[msg("The transfer hook can only be called by Token-2022 during a transfer")]
+    NotTransferring,
+}
+
+// The accounts Token-2022 has to resolve and pass to the transfer hook, the order must match `TransferHook`.
+// Any change here must bump EXTRA_ACCOUNT_METAS_VERSION and be pushed with update_extra_account_meta_list.
+pub fn extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+    Ok(vec![
+        // Sender Idendity Link
         ExtraAccountMeta::new_with_seeds(
             &[
                 Seed::Literal {
-                    bytes: b"identity".to_vec(),
+                    bytes: b"identity_link".to_vec(),
                 },
                 Seed::AccountKey { index: 0 },
             ],
             false, // is_signer
             false, // is_writable
         )?,
+        // Receiver Idendity Link
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"identity_link".to_vec(),
+                },
+                Seed::AccountKey { index: 2 },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Sender Idendity: the subject of the link is at offset 8
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"identity".to_vec(),
+                },
+                Seed::AccountData {
+                    account_index: 5,
+                    data_index: 8,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
         // Receiver Idendity
         ExtraAccountMeta::new_with_seeds(
             &[
                 Seed::Literal {
                     bytes: b"identity".to_vec(),
                 },
-                Seed::AccountKey { index: 2 },
+                Seed::AccountData {
+                    account_index: 6,
+                    data_index: 8,
+                    length: 32,
+                },
             ],
             false, // is_signer
             false, // is_writable
@@ -129,7 +249,206 @@ pub fn _initialize_extra_account_meta_list(
             false, // is_signer
             true,  // is_writable
         )?,
-    ];
+        // Program Config (global pause)
+        ExtraAccountMeta::new_with_seeds(
+            &[Seed::Literal {
+                bytes: b"config".to_vec(),
+            }],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Mint Config (mint pause, balance caps, holder count)
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"mint_config".to_vec(),
+                },
+                Seed::AccountKey { index: 1 },
+            ],
+            false, // is_signer
+            true,  // is_writable
+        )?,
+        // Jurisdiction Matrix
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"jurisdiction".to_vec(),
+                },
+                Seed::AccountKey { index: 1 },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Sender Claim Proof
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"claim_proof".to_vec(),
+                },
+                Seed::AccountKey { index: 0 },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Receiver Claim Proof
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"claim_proof".to_vec(),
+                },
+                Seed::AccountKey { index: 2 },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Sender Issuer Record: issuers[0].key of the sender idendity is at offset 8 + 32 + 32 + 4
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"issuer".to_vec(),
+                },
+                Seed::AccountData {
+                    account_index: 7,
+                    data_index: 76,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Sender Root Issuer Record: parent of the issuer record is at offset 8 + 32
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"issuer".to_vec(),
+                },
+                Seed::AccountData {
+                    account_index: 17,
+                    data_index: 40,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Receiver Issuer Record
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"issuer".to_vec(),
+                },
+                Seed::AccountData {
+                    account_index: 8,
+                    data_index: 76,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Receiver Root Issuer Record
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"issuer".to_vec(),
+                },
+                Seed::AccountData {
+                    account_index: 19,
+                    data_index: 40,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Sender Institution
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"institution".to_vec(),
+                },
+                Seed::AccountData {
+                    account_index: 5,
+                    data_index: 8,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Receiver Institution
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"institution".to_vec(),
+                },
+                Seed::AccountData {
+                    account_index: 6,
+                    data_index: 8,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Deny List: the sender, the receiver and their owners are searched in it
+        ExtraAccountMeta::new_with_seeds(
+            &[Seed::Literal {
+                bytes: b"deny_list".to_vec(),
+            }],
+            false, // is_signer
+            false, // is_writable
+        )?,
+        // Sender Holdings
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"holdings".to_vec(),
+                },
+                Seed::AccountKey { index: 1 },
+                Seed::AccountData {
+                    account_index: 5,
+                    data_index: 8,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            true,  // is_writable
+        )?,
+        // Receiver Holdings
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"holdings".to_vec(),
+                },
+                Seed::AccountKey { index: 1 },
+                Seed::AccountData {
+                    account_index: 6,
+                    data_index: 8,
+                    length: 32,
+                },
+            ],
+            false, // is_signer
+            true,  // is_writable
+        )?,
+        // Source Lockup
+        ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: b"lockup".to_vec(),
+                },
+                Seed::AccountKey { index: 0 },
+            ],
+            false, // is_signer
+            false, // is_writable
+        )?,
+    ])
+}
+
+pub fn _initialize_extra_account_meta_list(
+    ctx: Context<InitializeExtraAccountMetaList>,
+) -> Result<()> {
+    let account_metas = extra_account_metas()?;
 
     // calculate account size
     let account_size = ExtraAccountMetaList::size_of(account_metas.len())? as u64;
@@ -164,10 +483,65 @@ pub fn _initialize_extra_account_meta_list(
         &account_metas,
     )?;
 
+    ctx.accounts.mint_config.extra_account_metas_version = EXTRA_ACCOUNT_METAS_VERSION;
+
+    Ok(())
+}
+
+pub fn _update_extra_account_meta_list(ctx: Context<UpdateExtraAccountMetaList>) -> Result<()> {
+    let account_metas = extra_account_metas()?;
+
+    let extra_account_meta_list = &ctx.accounts.extra_account_meta_list;
+    let current_size = extra_account_meta_list.data_len();
+    let account_size = ExtraAccountMetaList::size_of(account_metas.len())?;
+    let lamports = Rent::get()?.minimum_balance(account_size);
+
+    if account_size > current_size {
+        // top up the rent before growing the account
+        let missing_lamports = lamports.saturating_sub(extra_account_meta_list.lamports());
+        if missing_lamports > 0 {
+            transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: extra_account_meta_list.to_account_info(),
+                    },
+                ),
+                missing_lamports,
+            )?;
+        }
+        extra_account_meta_list.realloc(account_size, false)?;
+    }
+
+    ExtraAccountMetaList::update::<ExecuteInstruction>(
+        &mut extra_account_meta_list.try_borrow_mut_data()?,
+        &account_metas,
+    )?;
+
+    if account_size < current_size {
+        // shrink the account and give the rent in excess back to the payer
+        extra_account_meta_list.realloc(account_size, false)?;
+        let excess_lamports = extra_account_meta_list.lamports().saturating_sub(lamports);
+        **extra_account_meta_list.try_borrow_mut_lamports()? -= excess_lamports;
+        **ctx.accounts.payer.try_borrow_mut_lamports()? += excess_lamports;
+    }
+
+    let mint_config = &mut ctx.accounts.mint_config;
+    mint_config.extra_account_metas_version = EXTRA_ACCOUNT_METAS_VERSION;
+
+    emit!(ExtraAccountMetaListUpdated {
+        mint: ctx.accounts.mint.key(),
+        version: EXTRA_ACCOUNT_METAS_VERSION,
+    });
+
     Ok(())
 }
 
 pub fn _transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
+    // Must be checked before anything else: a direct call would otherwise consume approvals and reset the recovery timer
+    check_is_transferring(&ctx)?;
+
     msg!("amount: {:?}", amount);
 
     let two_auth = &ctx.accounts.two_auth;
@@ -183,15 +557,46 @@ pub fn _transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
     // Check if seed is proper one for the account
     // Check if issuer is authorized
 
-    check_idendities(&ctx)?;
+    check_transfer_not_paused(
+        &ctx.accounts.config,
+        &ctx.accounts.mint_config,
+        &ctx.accounts.source_token.key(),
+        &ctx.accounts.destination_token.key(),
+    )?;
+    check_not_denied(
+        &ctx.accounts.deny_list,
+        &[
+            ctx.accounts.source_token.key(),
+            ctx.accounts.source_token.owner,
+            ctx.accounts.destination_token.key(),
+            ctx.accounts.destination_token.owner,
+        ],
+    )?;
+    let receiver_issuer = check_idendities(&ctx)?;
     check_not_recovered(&ctx)?;
+    check_not_locked(
+        &ctx.accounts.lockup,
+        ctx.accounts.source_token.amount,
+        Clock::get()?.unix_timestamp,
+    )?;
+    let receiver_balance = update_holdings(&ctx, amount)?;
+    check_balance_cap(&ctx, receiver_balance, receiver_issuer)?;
+    count_holders(ctx.accounts, amount)?;
 
     let need_two_auth = need_two_auth(&ctx, amount);
     if need_two_auth {
         msg!("Need two auth");
         check_approval(&ctx, amount)?;
+        // An approval is only valid for one transfer
         let transaction_approval = &mut ctx.accounts.transaction_approval;
         transaction_approval.active = false;
+
+        emit!(TransactionApprovalConsumed {
+            owner: ctx.accounts.owner.key(),
+            source: ctx.accounts.source_token.key(),
+            destination: ctx.accounts.destination_token.key(),
+            amount,
+        });
     }
 
     let last_tx = &mut ctx.accounts.last_tx;
@@ -200,24 +605,124 @@ pub fn _transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
     Ok(())
 }
 
+// Token-2022 sets the `transferring` flag of the source account for the time of the transfer hook CPI
 #[inline(always)]
-pub fn check_idendities(ctx: &Context<TransferHook>) -> Result<()> {
-    let issuer = &ctx.accounts.idendity_sender.issuers[0]; // Todo proper check of issuer
-    if issuer.active == false {
-        return Err(IdendityError::IdendityNotActive.into());
-    }
-    if issuer.expires_at < Clock::get()?.unix_timestamp {
-        return Err(IdendityError::IdendityExpired.into());
+pub fn check_is_transferring(ctx: &Context<TransferHook>) -> Result<()> {
+    let source_token_info = ctx.accounts.source_token.to_account_info();
+    let source_token_data = source_token_info.try_borrow_data()?;
+    let source_token = StateWithExtensions::<Token2022Account>::unpack(&source_token_data)?;
+    let transfer_hook_account = source_token.get_extension::<TransferHookAccount>()?;
+
+    if !bool::from(transfer_hook_account.transferring) {
+        return Err(TransferHookError::NotTransferring.into());
     }
+    Ok(())
+}
+
+// Returns the issuer of the receiver idendity whose chain was checked, its claims count for the balance cap
+#[inline(always)]
+pub fn check_idendities(ctx: &Context<TransferHook>) -> Result<Pubkey> {
+    let now = Clock::get()?.unix_timestamp;
+    let sender = &ctx.accounts.idendity_sender;
+    let receiver = &ctx.accounts.idendity_receiver;
+    let source_token = &ctx.accounts.source_token;
+    let destination_token = &ctx.accounts.destination_token;
+    check_linked(&ctx.accounts.link_sender, sender, source_token, &source_token.key())?;
+    check_linked(&ctx.accounts.link_receiver, receiver, destination_token, &destination_token.key())?;
+    check_institution_link(&ctx.accounts.link_sender, &ctx.accounts.institution_sender)?;
+    check_institution_link(&ctx.accounts.link_receiver, &ctx.accounts.institution_receiver)?;
+    sender.check_valid(now)?;
+    receiver.check_valid(now)?;
+    let sender_issuer = check_issuer_chain(
+        sender,
+        &ctx.accounts.issuer_record_sender,
+        &ctx.accounts.root_issuer_record_sender,
+        now,
+    )?;
+    let receiver_issuer = check_issuer_chain(
+        receiver,
+        &ctx.accounts.issuer_record_receiver,
+        &ctx.accounts.root_issuer_record_receiver,
+        now,
+    )?;
 
-    let receiver_id_issuer = &ctx.accounts.idendity_receiver.issuers[0];
-    if receiver_id_issuer.active == false {
-        return Err(IdendityError::IdendityNotActive.into());
+    let sender_proven = load_proven_claims(&ctx.accounts.claim_proof_sender)?;
+    let receiver_proven = load_proven_claims(&ctx.accounts.claim_proof_receiver)?;
+
+    let requirements = &ctx.accounts.mint_config.required_claims;
+    check_required_claims(sender, &sender_proven, &[sender_issuer], requirements, ClaimParty::Sender, now)?;
+    check_required_claims(receiver, &receiver_proven, &[receiver_issuer], requirements, ClaimParty::Receiver, now)?;
+
+    let sender_countries = valid_claim_values(sender, &sender_proven, &[sender_issuer], ClaimType::Country, now);
+    let receiver_countries = valid_claim_values(receiver, &receiver_proven, &[receiver_issuer], ClaimType::Country, now);
+    ctx.accounts
+        .jurisdiction_matrix
+        .check_transfer(&sender_countries, &receiver_countries)?;
+    Ok(receiver_issuer)
+}
+
+// An attestation of a sub-issuer is only valid while the sub-issuer is active and its root issuer is active.
+// Token-2022 only resolves the records of the first issuer of the idendity: the transfer fails closed when this issuer
+// no longer vouches for the idendity, even if another issuer does, as the chain of the other issuers can't be checked.
+// For the same reason, only the claims of this issuer count in a transfer. Returns the id of the checked issuer.
+pub fn check_issuer_chain(
+    idendity: &IdAccount,
+    issuer_record: &AccountInfo,
+    root_issuer_record: &AccountInfo,
+    now: i64,
+) -> Result<Pubkey> {
+    let first_issuer = idendity.issuers.first().ok_or(IdendityError::IdendityNotActive)?;
+    first_issuer.check_valid(now)?;
+    let record = load_issuer_record(issuer_record)?
+        .filter(|record| record.id == first_issuer.key)
+        .ok_or(IssuerError::IssuerNotActive)?;
+    let root = if record.is_root() {
+        None
+    } else {
+        load_issuer_record(root_issuer_record)?.filter(|root| root.id == record.parent)
+    };
+    record.check_can_attest(root.as_ref(), now)?;
+    Ok(record.id)
+}
+
+// The holdings of an idendity covering several token accounts follow the transfer. Returns the balance of the receiver
+// after the transfer, read from its token account when it has its own idendity, or None when the idendity doesn't change.
+#[inline(always)]
+pub fn update_holdings(ctx: &Context<TransferHook>, amount: u64) -> Result<Option<u64>> {
+    if ctx.accounts.link_sender.subject == ctx.accounts.link_receiver.subject {
+        return Ok(None);
     }
-    if receiver_id_issuer.expires_at < Clock::get()?.unix_timestamp {
-        return Err(IdendityError::IdendityExpired.into());
+    let holdings = move_holdings(&ctx.accounts.holdings_sender, &ctx.accounts.holdings_receiver, amount)?;
+    Ok(Some(holdings.unwrap_or(ctx.accounts.destination_token.amount)))
+}
+
+// The cap applies to the balance of the receiver idendity (see update_holdings)
+#[inline(always)]
+pub fn check_balance_cap(ctx: &Context<TransferHook>, receiver_balance: Option<u64>, receiver_issuer: Pubkey) -> Result<()> {
+    let Some(balance) = receiver_balance else {
+        return Ok(());
+    };
+    let now = Clock::get()?.unix_timestamp;
+    let receiver_proven = load_proven_claims(&ctx.accounts.claim_proof_receiver)?;
+    ctx.accounts
+        .mint_config
+        .balance_caps
+        .check_balance(&ctx.accounts.idendity_receiver, &receiver_proven, &[receiver_issuer], balance, now)
+}
+
+// The balances are read after the transfer: the sender may stop holding before the receiver starts holding
+#[inline(always)]
+pub fn count_holders(accounts: &mut TransferHook, amount: u64) -> Result<()> {
+    let source_balance = accounts.source_token.amount;
+    let destination_balance = accounts.destination_token.amount;
+    if accounts.source_token.key() == accounts.destination_token.key() {
+        return Ok(());
     }
-    Ok(())
+    let source = accounts.source_token.key();
+    let destination = accounts.destination_token.key();
+    let holder_count = &mut accounts.mint_config.holder_count;
+    holder_count.record(&source, source_balance.saturating_add(amount), source_balance)?;
+    holder_count.record(&destination, destination_balance.saturating_sub(amount), destination_balance)
 }
 
 #[inline(always)]
@@ -234,6 +739,10 @@ pub fn check_not_recovered(ctx: &Context<TransferHook>) -> Result<()> {
 
 #[inline(always)]
 pub fn check_approval(ctx: &Context<TransferHook>, amount: u64) -> Result<()> {
+    if !ctx.accounts.transaction_approval.active {
+        return Err(TwoAuthError::InactiveApproval.into());
+    }
+
     let transaction = &ctx.accounts.transaction_approval.transaction;
     let source_token = &ctx.accounts.source_token;
     let destination_token = &ctx.accounts.destination_token;
@@ -108,6 +108,8 @@ pub enum TwoAuthError {
     NotAuthorized,
     #[msg("The Approval has expired")]
     ExpiredApproval,
+    #[msg("No active approval: it has already been used or was never given")]
+    InactiveApproval,
 }
 
 pub fn _initialize_two_auth(
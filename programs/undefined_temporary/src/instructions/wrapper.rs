@@ -1,22 +1,77 @@
 use anchor_lang::{prelude::*, solana_program::program};
 use anchor_spl::{token_2022::spl_token_2022, token_interface::{Mint, TokenAccount, TokenInterface}};
 
+use crate::{add_to_holdings, check_issuer_chain, check_not_paused, load_proven_claims, lock_minted_tokens, ConfigError, IdAccount, IdentityLink, LockupSchedule, MintConfig, ProgramConfig, Role};
+
 
 #[derive(Accounts)]
 pub struct MintTo<'info> {
     #[account(mut, seeds=[b"mint"], bump)]
     pub mint: InterfaceAccount<'info, Mint>,
-    #[account(mut, token::mint = mint, token::authority = owner_token_account)]
+    #[account(mut, token::mint = mint)]
     pub to_token_account: InterfaceAccount<'info,TokenAccount>,
+    #[account(seeds = [b"config"], bump, constraint = config.has_role(Role::MinterAdmin, &minter.key()) @ ConfigError::MissingRole)]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [b"mint_config", mint.key().as_ref()], bump)]
+    pub mint_config: Account<'info, MintConfig>,
+    #[account(seeds = [b"identity_link", to_token_account.key().as_ref()], bump)]
+    pub link: Account<'info, IdentityLink>,
+    #[account(seeds = [b"identity", link.subject.as_ref()], bump)]
+    pub idendity: Account<'info, IdAccount>,
+    /// CHECK: Claims proven by the receiver, may not exist (see load_proven_claims)
+    #[account(seeds = [b"claim_proof", to_token_account.key().as_ref()], bump)]
+    pub claim_proof: UncheckedAccount<'info>,
+    /// CHECK: Issuer record of the first issuer of the idendity (see check_issuer_chain)
+    pub issuer_record: UncheckedAccount<'info>,
+    /// CHECK: Root issuer record of issuer_record, does not exist for a root issuer
+    pub root_issuer_record: UncheckedAccount<'info>,
+    /// CHECK: Holdings of the idendity, only exists for person and institution idendities (see load_holdings)
+    #[account(mut, seeds = [b"holdings", mint.key().as_ref(), link.subject.as_ref()], bump)]
+    pub holdings: UncheckedAccount<'info>,
+    /// CHECK: Lockup of the receiver, created or extended when the minted tokens are locked (see lock_minted_tokens)
+    #[account(mut, seeds = [b"lockup", to_token_account.key().as_ref()], bump)]
+    pub lockup: UncheckedAccount<'info>,
     #[account(mut)]
-    pub owner_token_account: Signer<'info>,
+    pub minter: Signer<'info>,
     pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
 }
 
 
 
 
-pub fn _mint_to(ctx: Context<MintTo>, amount: u64) -> Result<()> {
+pub fn _mint_to(ctx: Context<MintTo>, amount: u64, lockup: Option<LockupSchedule>) -> Result<()> {
+    check_not_paused(&ctx.accounts.config, &ctx.accounts.mint_config)?;
+
+    // The minted tokens count in the holdings of the receiver idendity
+    let balance = match add_to_holdings(&ctx.accounts.holdings, amount)? {
+        Some(holdings) => holdings,
+        None => ctx.accounts.to_token_account.amount.saturating_add(amount),
+    };
+    let now = Clock::get()?.unix_timestamp;
+    let proven = load_proven_claims(&ctx.accounts.claim_proof)?;
+    // The claims only raise the cap while the chain of their issuer is valid in the issuer registry
+    let checked_issuers: Vec<Pubkey> = check_issuer_chain(&ctx.accounts.idendity, &ctx.accounts.issuer_record, &ctx.accounts.root_issuer_record, now)
+        .ok()
+        .into_iter()
+        .collect();
+    ctx.accounts.mint_config.balance_caps.check_balance(&ctx.accounts.idendity, &proven, &checked_issuers, balance, now)?;
+
+    let balance_before = ctx.accounts.to_token_account.amount;
+    ctx.accounts.mint_config.holder_count.record(&ctx.accounts.to_token_account.key(), balance_before, balance_before.saturating_add(amount))?;
+
+    // The minted tokens are locked in the same instruction, they can't be transferred before
+    if let Some(schedule) = lockup {
+        lock_minted_tokens(
+            &ctx.accounts.lockup,
+            ctx.accounts.to_token_account.key(),
+            amount,
+            &schedule,
+            &ctx.accounts.minter,
+            &ctx.accounts.system_program,
+            ctx.bumps.lockup,
+        )?;
+    }
 
     let signer : &[&[&[u8]]] = &[&[b"mint",&[ctx.bumps.mint]]];
 
@@ -6,6 +6,7 @@ pub mod state;
 use anchor_lang::prelude::*;
 
 pub use constants::*;
+pub use error::*;
 pub use instructions::*;
 pub use state::*;
 
@@ -30,13 +31,239 @@ pub mod undefined_temporary {
         id_instructions::_add_issuer(ctx, id_validity_duration)
     }
 
+    /* Initializes a Digital Idendity from an attestation signed off-chain by an idendity issuer, the user pays the rent. */
+    pub fn add_attested_idendity(
+        ctx: Context<InitializeAttestedId>,
+        attestation: IdendityAttestation,
+    ) -> Result<()> {
+        attestation_instructions::_add_attested_idendity(ctx, attestation)
+    }
+
+    pub fn remove_issuer_from_idendity(ctx: Context<RemoveIssuer>) -> Result<()> {
+        id_instructions::_remove_issuer(ctx)
+    }
+
+    pub fn detach_issuer_from_idendity(ctx: Context<DetachIssuer>) -> Result<()> {
+        id_instructions::_detach_issuer(ctx)
+    }
+
+    // Person Idendity Instructions
+
+    /* Initializes a Digital Idendity of a person, covering all the token accounts it links. */
+    pub fn add_person_identity(ctx: Context<InitializePersonId>, id_validity_duration: i64) -> Result<()> {
+        identity_link_instructions::_initialize_person_id(ctx, id_validity_duration)
+    }
+
+    pub fn link_token_account(ctx: Context<LinkTokenAccount>) -> Result<()> {
+        identity_link_instructions::_link_token_account(ctx)
+    }
+
+    pub fn unlink_token_account(ctx: Context<UnlinkTokenAccount>) -> Result<()> {
+        identity_link_instructions::_unlink_token_account(ctx)
+    }
+
+    // Institution Instructions
+
+    /* Initializes the Digital Idendity of a legal entity, covering the token accounts of its authorized signers and program vaults. */
+    pub fn add_institution_identity(
+        ctx: Context<InitializeInstitutionId>,
+        id_validity_duration: i64,
+        authorized_signers: Vec<Pubkey>,
+    ) -> Result<()> {
+        institution_instructions::_initialize_institution_id(ctx, id_validity_duration, authorized_signers)
+    }
+
+    pub fn set_authorized_signers(ctx: Context<SetAuthorizedSigners>, authorized_signers: Vec<Pubkey>) -> Result<()> {
+        institution_instructions::_set_authorized_signers(ctx, authorized_signers)
+    }
+
+    pub fn link_institution_token_account(ctx: Context<LinkInstitutionTokenAccount>) -> Result<()> {
+        institution_instructions::_link_institution_token_account(ctx)
+    }
+
+    /* Links a token account owned by a PDA of another program (escrow, AMM pool...), the seeds include the bump. */
+    pub fn link_program_vault(ctx: Context<LinkProgramVault>, vault_seeds: Vec<Vec<u8>>) -> Result<()> {
+        institution_instructions::_link_program_vault(ctx, vault_seeds)
+    }
+
+    pub fn unlink_institution_token_account(ctx: Context<UnlinkInstitutionTokenAccount>) -> Result<()> {
+        institution_instructions::_unlink_institution_token_account(ctx)
+    }
+
+    // Claim Instructions
+
+    pub fn set_claim(
+        ctx: Context<SetClaim>,
+        claim_type: ClaimType,
+        value: u16,
+        validity_duration: i64,
+    ) -> Result<()> {
+        claim_instructions::_set_claim(ctx, claim_type, value, validity_duration)
+    }
+
+    pub fn remove_claim(ctx: Context<RemoveClaim>, claim_type: ClaimType) -> Result<()> {
+        claim_instructions::_remove_claim(ctx, claim_type)
+    }
+
+    pub fn set_required_claims(ctx: Context<SetRequiredClaims>, required_claims: Vec<ClaimRequirement>) -> Result<()> {
+        claim_instructions::_set_required_claims(ctx, required_claims)
+    }
+
+    pub fn set_claim_commitment(
+        ctx: Context<SetClaimCommitment>,
+        root: [u8; 32],
+        validity_duration: i64,
+    ) -> Result<()> {
+        commitment_instructions::_set_claim_commitment(ctx, root, validity_duration)
+    }
+
+    pub fn prove_claim(
+        ctx: Context<ProveClaim>,
+        claim_type: ClaimType,
+        value: u16,
+        salt: [u8; 32],
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        commitment_instructions::_prove_claim(ctx, claim_type, value, salt, proof)
+    }
+
+    // Jurisdiction Instructions
+
+    pub fn initialize_jurisdiction_matrix(ctx: Context<InitializeJurisdictionMatrix>) -> Result<()> {
+        jurisdiction_instructions::_initialize_jurisdiction_matrix(ctx)
+    }
+
+    pub fn add_jurisdiction(ctx: Context<UpdateJurisdictionMatrix>, country: u16) -> Result<()> {
+        jurisdiction_instructions::_add_jurisdiction(ctx, country)
+    }
+
+    pub fn set_jurisdiction_sanctioned(
+        ctx: Context<UpdateJurisdictionMatrix>,
+        country: u16,
+        sanctioned: bool,
+    ) -> Result<()> {
+        jurisdiction_instructions::_set_jurisdiction_sanctioned(ctx, country, sanctioned)
+    }
+
+    pub fn set_jurisdiction_pair(
+        ctx: Context<UpdateJurisdictionMatrix>,
+        from: u16,
+        to: u16,
+        blocked: bool,
+    ) -> Result<()> {
+        jurisdiction_instructions::_set_jurisdiction_pair(ctx, from, to, blocked)
+    }
+
+    pub fn set_block_unlisted_jurisdictions(
+        ctx: Context<UpdateJurisdictionMatrix>,
+        block_unlisted: bool,
+    ) -> Result<()> {
+        jurisdiction_instructions::_set_block_unlisted_jurisdictions(ctx, block_unlisted)
+    }
+
+    // Owner Consent Instructions
+
+    pub fn grant_issuer_consent(ctx: Context<GrantIssuerConsent>, issuer: Pubkey) -> Result<()> {
+        consent_instructions::_grant_issuer_consent(ctx, issuer)
+    }
+
+    pub fn revoke_issuer_consent(ctx: Context<RevokeIssuerConsent>) -> Result<()> {
+        consent_instructions::_revoke_issuer_consent(ctx)
+    }
+
+    pub fn set_owner_consent_required(ctx: Context<SetOwnerConsentRequired>, required: bool) -> Result<()> {
+        consent_instructions::_set_owner_consent_required(ctx, required)
+    }
+
+    // Config Instructions
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>) -> Result<()> {
+        config_instructions::_initialize_config(ctx)
+    }
+
+    pub fn grant_role(ctx: Context<UpdateRole>, role: Role, account: Pubkey) -> Result<()> {
+        config_instructions::_grant_role(ctx, role, account)
+    }
+
+    pub fn revoke_role(ctx: Context<UpdateRole>, role: Role, account: Pubkey) -> Result<()> {
+        config_instructions::_revoke_role(ctx, role, account)
+    }
+
+    pub fn propose_super_admin(ctx: Context<ProposeSuperAdmin>, new_super_admin: Pubkey) -> Result<()> {
+        config_instructions::_propose_super_admin(ctx, new_super_admin)
+    }
+
+    pub fn accept_super_admin(ctx: Context<AcceptSuperAdmin>) -> Result<()> {
+        config_instructions::_accept_super_admin(ctx)
+    }
+
+    // Issuer Registry Instructions
+
+    pub fn register_issuer(ctx: Context<RegisterIssuer>, issuer: Pubkey) -> Result<()> {
+        issuer_registry_instructions::_register_issuer(ctx, issuer)
+    }
+
+    pub fn revoke_issuer(ctx: Context<RevokeIssuer>) -> Result<()> {
+        issuer_registry_instructions::_revoke_issuer(ctx)
+    }
+
+    pub fn register_sub_issuer(
+        ctx: Context<RegisterSubIssuer>,
+        sub_issuer: Pubkey,
+        scope: IssuerScope,
+        expires_at: i64,
+    ) -> Result<()> {
+        issuer_registry_instructions::_register_sub_issuer(ctx, sub_issuer, scope, expires_at)
+    }
+
+    pub fn revoke_sub_issuer(ctx: Context<RevokeSubIssuer>) -> Result<()> {
+        issuer_registry_instructions::_revoke_sub_issuer(ctx)
+    }
+
+    pub fn rotate_issuer_key(ctx: Context<RotateIssuerKey>) -> Result<()> {
+        issuer_registry_instructions::_rotate_issuer_key(ctx)
+    }
+
+    pub fn set_issuer_max_validity(ctx: Context<SetIssuerMaxValidity>, max_validity_duration: i64) -> Result<()> {
+        issuer_registry_instructions::_set_issuer_max_validity(ctx, max_validity_duration)
+    }
+
+    pub fn renew_identity(ctx: Context<UpdateIdendity>, id_validity_duration: i64) -> Result<()> {
+        id_instructions::_renew_idendity(ctx, id_validity_duration)
+    }
+
+    pub fn set_validity_bounds(
+        ctx: Context<SetValidityBounds>,
+        min_validity_duration: i64,
+        max_validity_duration: i64,
+    ) -> Result<()> {
+        id_instructions::_set_validity_bounds(ctx, min_validity_duration, max_validity_duration)
+    }
+
+    pub fn suspend_identity(ctx: Context<UpdateIdendity>, reason_code: u16) -> Result<()> {
+        id_instructions::_suspend_idendity(ctx, reason_code)
+    }
+
+    pub fn reinstate_identity(ctx: Context<UpdateIdendity>, reason_code: u16) -> Result<()> {
+        id_instructions::_reinstate_idendity(ctx, reason_code)
+    }
+
+    pub fn revoke_identity(ctx: Context<UpdateIdendity>, reason_code: u16) -> Result<()> {
+        id_instructions::_revoke_idendity(ctx, reason_code)
+    }
+
     // Initialize Token Mint
     pub fn initialize_token_mint(ctx: Context<InitializeTokenMint>) -> Result<()> {
         initialize_mint::_initialize_token_mint(ctx)
     }
 
-    pub fn mint_to(ctx: Context<MintTo>, amount: u64) -> Result<()> {
-        wrapper::_mint_to(ctx, amount)
+    pub fn migrate_mint_config(ctx: Context<MigrateMintConfig>) -> Result<()> {
+        initialize_mint::_migrate_mint_config(ctx)
+    }
+
+    /* The minted tokens are locked on the token account when a lockup schedule is given. */
+    pub fn mint_to(ctx: Context<MintTo>, amount: u64, lockup: Option<LockupSchedule>) -> Result<()> {
+        wrapper::_mint_to(ctx, amount, lockup)
     }
 
     // Recovery Instructions
@@ -77,10 +304,125 @@ pub mod undefined_temporary {
         transfer_hook::_initialize_extra_account_meta_list(ctx)
     }
 
+    pub fn update_extra_account_meta_list(
+        ctx: Context<UpdateExtraAccountMetaList>,
+    ) -> Result<()> {
+        transfer_hook::_update_extra_account_meta_list(ctx)
+    }
+
     pub fn add_token_account(ctx: Context<AddTokenAccount>) -> Result<()> {
         add_token_account::_add_token_account(ctx)
     }
 
+    // Compliance Instructions
+
+    pub fn freeze_account(
+        ctx: Context<FreezeTokenAccount>,
+        reason_code: u16,
+        case_reference: String,
+    ) -> Result<()> {
+        compliance_instructions::_freeze_account(ctx, reason_code, case_reference)
+    }
+
+    pub fn thaw_account(
+        ctx: Context<ThawTokenAccount>,
+        reason_code: u16,
+        case_reference: String,
+    ) -> Result<()> {
+        compliance_instructions::_thaw_account(ctx, reason_code, case_reference)
+    }
+
+    pub fn forced_transfer(ctx: Context<ForcedTransfer>, amount: u64, case_id: u64) -> Result<()> {
+        compliance_instructions::_forced_transfer(ctx, amount, case_id)
+    }
+
+    // Deny List Instructions
+
+    pub fn initialize_deny_list(ctx: Context<InitializeDenyList>) -> Result<()> {
+        deny_list_instructions::_initialize_deny_list(ctx)
+    }
+
+    pub fn add_to_deny_list(
+        ctx: Context<AddToDenyList>,
+        address: Pubkey,
+        reason_code: u16,
+        list_source: String,
+    ) -> Result<()> {
+        deny_list_instructions::_add_to_deny_list(ctx, address, reason_code, list_source)
+    }
+
+    pub fn remove_from_deny_list(ctx: Context<RemoveFromDenyList>, address: Pubkey) -> Result<()> {
+        deny_list_instructions::_remove_from_deny_list(ctx, address)
+    }
+
+    /* Denies several addresses from the same list, their entries are passed as remaining accounts. */
+    pub fn import_deny_list<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ImportDenyList<'info>>,
+        addresses: Vec<DeniedAddress>,
+        list_source: String,
+    ) -> Result<()> {
+        deny_list_instructions::_import_deny_list(ctx, addresses, list_source)
+    }
+
+    // Balance Cap Instructions
+
+    pub fn set_balance_caps(
+        ctx: Context<SetBalanceCaps>,
+        default_max_balance: u64,
+        tiers: Vec<BalanceCapTier>,
+    ) -> Result<()> {
+        balance_cap_instructions::_set_balance_caps(ctx, default_max_balance, tiers)
+    }
+
+    // Holder Count Instructions
+
+    pub fn set_max_holders(ctx: Context<SetMaxHolders>, max_holders: u64) -> Result<()> {
+        holder_count_instructions::_set_max_holders(ctx, max_holders)
+    }
+
+    pub fn start_holder_recount(ctx: Context<RecountHolders>) -> Result<()> {
+        holder_count_instructions::_start_holder_recount(ctx)
+    }
+
+    /* Counts the holders among the token accounts passed as remaining accounts, sorted by address. */
+    pub fn recount_holders<'info>(ctx: Context<'_, '_, 'info, 'info, RecountHolders<'info>>) -> Result<()> {
+        holder_count_instructions::_recount_holders(ctx)
+    }
+
+    pub fn finish_holder_recount(ctx: Context<RecountHolders>) -> Result<()> {
+        holder_count_instructions::_finish_holder_recount(ctx)
+    }
+
+    // Lockup Instructions
+
+    pub fn close_lockup(ctx: Context<CloseLockup>) -> Result<()> {
+        lockup_instructions::_close_lockup(ctx)
+    }
+
+    // Pause Instructions
+
+    pub fn set_mint_paused(ctx: Context<SetMintPaused>, paused: bool) -> Result<()> {
+        pause_instructions::_set_mint_paused(ctx, paused)
+    }
+
+    pub fn set_global_paused(ctx: Context<SetGlobalPaused>, paused: bool) -> Result<()> {
+        pause_instructions::_set_global_paused(ctx, paused)
+    }
+
+    pub fn add_pause_exemption(ctx: Context<UpdatePauseExemption>, token_account: Pubkey) -> Result<()> {
+        pause_instructions::_add_pause_exemption(ctx, token_account)
+    }
+
+    pub fn remove_pause_exemption(ctx: Context<UpdatePauseExemption>, token_account: Pubkey) -> Result<()> {
+        pause_instructions::_remove_pause_exemption(ctx, token_account)
+    }
+
+    // Prune Instructions
+
+    pub fn prune_expired_identity(ctx: Context<PruneExpiredIdendity>) -> Result<()> {
+        prune_instructions::_prune_expired_idendity(ctx)
+    }
+
     pub fn fallback<'info>(
         program_id: &Pubkey,
         accounts: &'info [AccountInfo<'info>],
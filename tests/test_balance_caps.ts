@@ -0,0 +1,115 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import { find_issuer_chain } from "./test_config";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  createTransferCheckedWithTransferHookInstruction,
+} from "@solana/spl-token";
+import { sendAndConfirmTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+
+const NO_CAP = new anchor.BN("18446744073709551615");
+
+async function set_balance_caps(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  default_max_balance: anchor.BN
+) {
+  // Only accredited holders are not capped
+  await program.methods
+    .setBalanceCaps(default_max_balance, [
+      { claimType: { accredited: {} }, value: 1, maxBalance: NO_CAP },
+    ])
+    .accounts({
+      mint: args.mint,
+      compliance: args.users[0].owner.publicKey,
+    })
+    .signers([args.users[0].owner])
+    .rpc();
+}
+
+export async function test_balance_caps(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+
+  try {
+    await set_balance_caps(args, program, new anchor.BN(0));
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    let transferInstructionWithHelper =
+      await createTransferCheckedWithTransferHookInstruction(
+        program.provider.connection,
+        user1.token_account,
+        args.mint,
+        user2.token_account,
+        user1.owner.publicKey,
+        BigInt(amount),
+        decimals,
+        [],
+        "confirmed",
+        TOKEN_2022_PROGRAM_ID
+      );
+
+    await sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(transferInstructionWithHelper),
+      [user1.owner]
+    );
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect(
+      ((error as anchor.AnchorError).logs as Array<string>).some((log) =>
+        log.includes("Error Code: BalanceCapExceeded.")
+      )
+    ).to.be.true;
+  }
+
+  try {
+    await program.methods
+      .mintTo(new anchor.BN(amount), null)
+      .accounts({
+        mint: args.mint,
+        toTokenAccount: user2.token_account,
+        ...(await find_issuer_chain(program, user2.token_account)),
+        minter: user1.owner.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user1.owner])
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "BalanceCapExceeded"
+    );
+  }
+
+  try {
+    await set_balance_caps(args, program, NO_CAP);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const caps = (
+    await program.account.mintConfig.fetch(
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from(anchor.utils.bytes.utf8.encode("mint_config")),
+          args.mint.toBuffer(),
+        ],
+        program.programId
+      )[0]
+    )
+  ).balanceCaps;
+  expect(caps.defaultMaxBalance.eq(NO_CAP)).to.be.true;
+  expect(caps.tiers.length).to.be.equal(1);
+}
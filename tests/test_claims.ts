@@ -0,0 +1,313 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import { find_issuer_record, register_issuer } from "./test_config";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  createTransferCheckedWithTransferHookInstruction,
+} from "@solana/spl-token";
+import { sendAndConfirmTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+import { createHash, randomBytes } from "crypto";
+
+async function set_claim(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  user: number,
+  claim_type: any,
+  value: number
+) {
+  let user_to = args.users[user];
+  const tx = await program.methods
+    .setClaim(claim_type, value, new anchor.BN(100000))
+    .accounts({
+      issuer: args.issuer.publicKey,
+      issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+      idendity: user_to.idendity,
+      parentIssuerRecord: null,
+      subject: user_to.token_account,
+    })
+    .signers([args.issuer])
+    .rpc();
+  console.log("Your transaction signature for set claim", tx);
+}
+
+async function set_required_claims(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  required_claims: any[]
+) {
+  const tx = await program.methods
+    .setRequiredClaims(required_claims)
+    .accounts({
+      mint: args.mint,
+      superAdmin: args.admin.publicKey,
+    })
+    .signers([args.admin])
+    .rpc();
+  console.log("Your transaction signature for set required claims", tx);
+}
+
+export async function test_set_claim(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user2 = args.users[1];
+  try {
+    await set_claim(args, program, 1, { country: {} }, 250);
+    // Updating the claim increments its version
+    await set_claim(args, program, 1, { country: {} }, 840);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.claims.length).to.be.equal(1);
+  expect(idendity.claims[0].value).to.be.equal(840);
+  expect(idendity.claims[0].version).to.be.equal(2);
+
+  try {
+    await set_claim(args, program, 1, { accredited: {} }, 2);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "InvalidClaimValue"
+    );
+  }
+}
+
+export async function test_required_claims_transfer(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+
+  // Receiver must be accredited in US, user2 only has the country claim
+  try {
+    await set_required_claims(args, program, [
+      {
+        claimType: { country: {} },
+        comparison: { equal: {} },
+        value: 840,
+        party: { receiver: {} },
+      },
+      {
+        claimType: { accredited: {} },
+        comparison: { equal: {} },
+        value: 1,
+        party: { receiver: {} },
+      },
+    ]);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  const transfer_to_user2 = async () => {
+    let transferInstructionWithHelper =
+      await createTransferCheckedWithTransferHookInstruction(
+        program.provider.connection,
+        user1.token_account,
+        args.mint,
+        user2.token_account,
+        user1.owner.publicKey,
+        BigInt(amount),
+        decimals,
+        [],
+        "confirmed",
+        TOKEN_2022_PROGRAM_ID
+      );
+
+    return await sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(transferInstructionWithHelper),
+      [user1.owner]
+    );
+  };
+  const expect_missing_required_claim = (error: any) =>
+    expect(
+      ((error as anchor.AnchorError).logs as Array<string>).some((log) =>
+        log.includes("Error Code: MissingRequiredClaim.")
+      )
+    ).to.be.true;
+
+  try {
+    const txSig = await transfer_to_user2();
+    console.log("Transfer Signature: NOT NORMAL", txSig);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect_missing_required_claim(error);
+  }
+
+  // The registry chain of the other issuers of an idendity can't be checked in a transfer: their claims don't count
+  const other_issuer = anchor.web3.Keypair.generate();
+  const other_issuer_record = find_issuer_record(
+    program,
+    other_issuer.publicKey
+  );
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: other_issuer.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    await register_issuer(args, program, user1.owner, other_issuer.publicKey);
+    await program.methods
+      .addIssuerToIdendity(new anchor.BN(1000))
+      .accounts({
+        issuer: other_issuer.publicKey,
+        issuerRecord: other_issuer_record,
+        idendity: user2.idendity,
+        parentIssuerRecord: null,
+        owner: user2.owner.publicKey,
+        tokenAccount: user2.token_account,
+        consent: null,
+      })
+      .signers([other_issuer])
+      .rpc();
+    await program.methods
+      .setClaim({ accredited: {} }, 1, new anchor.BN(1000))
+      .accounts({
+        issuer: other_issuer.publicKey,
+        issuerRecord: other_issuer_record,
+        idendity: user2.idendity,
+        parentIssuerRecord: null,
+        subject: user2.token_account,
+      })
+      .signers([other_issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    const txSig = await transfer_to_user2();
+    console.log("Transfer Signature: NOT NORMAL", txSig);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect_missing_required_claim(error);
+  }
+
+  try {
+    await program.methods
+      .removeIssuerFromIdendity()
+      .accounts({
+        issuer: other_issuer.publicKey,
+        issuerRecord: other_issuer_record,
+        idendity: user2.idendity,
+        tokenAccount: user2.token_account,
+      })
+      .signers([other_issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    await set_required_claims(args, program, []);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+}
+
+function sha256(...parts: Buffer[]): Buffer {
+  return createHash("sha256").update(Buffer.concat(parts)).digest();
+}
+
+// Same leaf and node encoding as commitment_instructions.rs
+function claim_leaf(claim_type: number, value: number, salt: Buffer): Buffer {
+  const value_bytes = Buffer.alloc(2);
+  value_bytes.writeUInt16LE(value);
+  return sha256(
+    Buffer.from([0]),
+    Buffer.from([claim_type]),
+    value_bytes,
+    salt
+  );
+}
+
+function merkle_node(a: Buffer, b: Buffer): Buffer {
+  return Buffer.compare(a, b) <= 0
+    ? sha256(Buffer.from([1]), a, b)
+    : sha256(Buffer.from([1]), b, a);
+}
+
+export async function test_prove_claim(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user1 = args.users[0];
+  const accredited_salt = randomBytes(32);
+  const age_salt = randomBytes(32);
+  // ClaimType: Accredited = 2, AgeOver18 = 3
+  const accredited_leaf = claim_leaf(2, 1, accredited_salt);
+  const age_leaf = claim_leaf(3, 1, age_salt);
+  const root = merkle_node(accredited_leaf, age_leaf);
+
+  const [claim_proof] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("claim_proof")),
+      user1.token_account.toBuffer(),
+    ],
+    program.programId
+  );
+  const prove = (value: number) =>
+    program.methods
+      .proveClaim({ accredited: {} }, value, [...accredited_salt], [
+        [...age_leaf],
+      ])
+      .accounts({
+        owner: user1.owner.publicKey,
+        idendity: user1.idendity,
+        tokenAccount: user1.token_account,
+        claimProof: claim_proof,
+      })
+      .signers([user1.owner])
+      .rpc();
+
+  try {
+    await program.methods
+      .setClaimCommitment([...root], new anchor.BN(100000))
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        idendity: user1.idendity,
+        parentIssuerRecord: null,
+        subject: user1.token_account,
+      })
+      .signers([args.issuer])
+      .rpc();
+    await prove(1);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const proof = await program.account.claimProof.fetch(claim_proof);
+  expect(proof.proven.length).to.be.equal(1);
+  expect(proof.proven[0].value).to.be.equal(1);
+  expect(proof.proven[0].issuer.toBase58()).to.be.equal(
+    args.issuer.publicKey.toBase58()
+  );
+
+  try {
+    // The leaf does not match the committed value
+    await prove(0);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "InvalidClaimProof"
+    );
+  }
+}
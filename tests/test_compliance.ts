@@ -0,0 +1,314 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import { find_issuer_record, find_linked_holdings } from "./test_config";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  getAccount,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+function get_freeze_record(
+  program: anchor.Program<UndefinedTemporary>,
+  token_account: anchor.web3.PublicKey
+) {
+  const [freeze_record] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("freeze_record")),
+      token_account.toBuffer(),
+    ],
+    program.programId
+  );
+  return freeze_record;
+}
+
+export async function test_compliance_freeze_thaw(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  const freeze_record = get_freeze_record(program, user2.token_account);
+
+  try {
+    const tx = await program.methods
+      .freezeAccount(1, "CASE-2024-001")
+      .accounts({
+        authority: user1.owner.publicKey,
+        freezeRecord: freeze_record,
+        mint: args.mint,
+        tokenAccount: user2.token_account,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user1.owner])
+      .rpc();
+
+    console.log("Your transaction signature for freeze", tx);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  let record = await program.account.freezeRecord.fetch(freeze_record);
+  expect(record.frozen).to.be.true;
+  expect(record.reasonCode).to.be.equal(1);
+  expect(record.caseReference).to.be.equal("CASE-2024-001");
+  let token_account = await getAccount(
+    program.provider.connection,
+    user2.token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(token_account.isFrozen).to.be.true;
+
+  try {
+    const tx = await program.methods
+      .thawAccount(2, "CASE-2024-001")
+      .accounts({
+        authority: user1.owner.publicKey,
+        freezeRecord: freeze_record,
+        mint: args.mint,
+        tokenAccount: user2.token_account,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user1.owner])
+      .rpc();
+
+    console.log("Your transaction signature for thaw", tx);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  record = await program.account.freezeRecord.fetch(freeze_record);
+  expect(record.frozen).to.be.false;
+  expect(record.reasonCode).to.be.equal(2);
+  token_account = await getAccount(
+    program.provider.connection,
+    user2.token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(token_account.isFrozen).to.be.false;
+}
+
+// A token account frozen by default can be frozen by Compliance before its owner is verified:
+// the issuance of the idendity does not thaw it
+export async function test_compliance_freeze_before_issuance(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  const connection = program.provider.connection;
+  const compliance = args.users[0].owner;
+  const owner = anchor.web3.Keypair.generate();
+  const token_account = getAssociatedTokenAddressSync(
+    args.mint,
+    owner.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID
+  );
+  const freeze_account = () =>
+    program.methods
+      .freezeAccount(4, "CASE-2024-004")
+      .accounts({
+        authority: compliance.publicKey,
+        mint: args.mint,
+        tokenAccount: token_account,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([compliance])
+      .rpc();
+
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: owner.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    await program.methods
+      .addTokenAccount()
+      .accounts({
+        owner: owner.publicKey,
+        tokenAccount: token_account,
+        mint: args.mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+    await freeze_account();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const record = await program.account.freezeRecord.fetch(
+    get_freeze_record(program, token_account)
+  );
+  expect(record.frozen).to.be.true;
+  expect(record.reasonCode).to.be.equal(4);
+
+  try {
+    await freeze_account();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "AlreadyFrozen"
+    );
+  }
+
+  try {
+    await program.methods
+      .addIdendity(new anchor.BN(100000))
+      .accounts({
+        parentIssuerRecord: null,
+        owner: owner.publicKey,
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        mint: args.mint,
+        tokenAccount: token_account,
+        previousHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          token_account
+        ),
+        consent: null,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([args.issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const account = await getAccount(
+    connection,
+    token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(account.isFrozen).to.be.true;
+}
+
+export async function test_compliance_freeze_not_authority(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user2 = args.users[1];
+  let issuer = args.issuer;
+  try {
+    const tx = await program.methods
+      .freezeAccount(1, "CASE-2024-002")
+      .accounts({
+        authority: issuer.publicKey,
+        freezeRecord: get_freeze_record(program, user2.token_account),
+        mint: args.mint,
+        tokenAccount: user2.token_account,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([issuer])
+      .rpc();
+
+    console.log("Your transaction signature for freeze", tx);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "MissingRole"
+    );
+  }
+}
+
+export async function test_forced_transfer(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  const case_id = new anchor.BN(1);
+  const [seizure_record] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("seizure_record")),
+      user1.token_account.toBuffer(),
+      case_id.toArrayLike(Buffer, "le", 8),
+    ],
+    program.programId
+  );
+
+  const source_before = await getAccount(
+    program.provider.connection,
+    user1.token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  const destination_before = await getAccount(
+    program.provider.connection,
+    user2.token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+
+  try {
+    const tx = await program.methods
+      .forcedTransfer(new anchor.BN(amount), case_id)
+      .accounts({
+        authority: user1.owner.publicKey,
+        seizureRecord: seizure_record,
+        mint: args.mint,
+        sourceToken: user1.token_account,
+        destinationToken: user2.token_account,
+        sourceHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          user1.token_account
+        ),
+        destinationHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          user2.token_account
+        ),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user1.owner])
+      .rpc();
+
+    console.log("Your transaction signature for forced transfer", tx);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  const source_after = await getAccount(
+    program.provider.connection,
+    user1.token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  const destination_after = await getAccount(
+    program.provider.connection,
+    user2.token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(source_before.amount - source_after.amount).to.be.equal(
+    BigInt(amount)
+  );
+  expect(destination_after.amount - destination_before.amount).to.be.equal(
+    BigInt(amount)
+  );
+
+  const record = await program.account.seizureRecord.fetch(seizure_record);
+  expect(record.amount.toNumber()).to.be.equal(amount);
+  expect(record.source.toBase58()).to.be.equal(user1.token_account.toBase58());
+  expect(record.destination.toBase58()).to.be.equal(
+    user2.token_account.toBase58()
+  );
+  expect(record.authority.toBase58()).to.be.equal(
+    user1.owner.publicKey.toBase58()
+  );
+}
@@ -0,0 +1,266 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import { expect } from "chai";
+
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new anchor.web3.PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+
+export function get_config(program: anchor.Program<UndefinedTemporary>) {
+  const [config] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode("config"))],
+    program.programId
+  );
+  return config;
+}
+
+export async function init_config(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  const [program_data] = anchor.web3.PublicKey.findProgramAddressSync(
+    [program.programId.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+  );
+  try {
+    const tx = await program.methods
+      .initializeConfig()
+      .accounts({
+        config: get_config(program),
+        authority: args.admin.publicKey,
+        program: program.programId,
+        programData: program_data,
+      })
+      .signers([args.admin])
+      .rpc();
+
+    console.log("Your transaction signature for config init", tx);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  const config = await program.account.programConfig.fetch(
+    get_config(program)
+  );
+  expect(config.superAdmin.toBase58()).to.be.equal(
+    args.admin.publicKey.toBase58()
+  );
+}
+
+export async function grant_role(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  role: any,
+  account: anchor.web3.PublicKey
+) {
+  try {
+    const tx = await program.methods
+      .grantRole(role, account)
+      .accounts({
+        config: get_config(program),
+        superAdmin: args.admin.publicKey,
+      })
+      .signers([args.admin])
+      .rpc();
+
+    console.log("Your transaction signature for grant role", tx);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+}
+
+// Holdings of the subject a token account is linked to (an empty account if it is not linked)
+export async function find_linked_holdings(
+  program: anchor.Program<UndefinedTemporary>,
+  mint: anchor.web3.PublicKey,
+  token_account: anchor.web3.PublicKey
+) {
+  const find_pda = (seeds: Buffer[]) =>
+    anchor.web3.PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+  const link = await program.account.identityLink.fetchNullable(
+    find_pda([
+      Buffer.from(anchor.utils.bytes.utf8.encode("identity_link")),
+      token_account.toBuffer(),
+    ])
+  );
+  const subject = link ? link.subject : anchor.web3.PublicKey.default;
+  return find_pda([
+    Buffer.from(anchor.utils.bytes.utf8.encode("holdings")),
+    mint.toBuffer(),
+    subject.toBuffer(),
+  ]);
+}
+
+// The issuer record is seeded by the issuer id, the first key of the issuer
+export function find_issuer_record(
+  program: anchor.Program<UndefinedTemporary>,
+  issuer_id: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("issuer")),
+      issuer_id.toBuffer(),
+    ],
+    program.programId
+  )[0];
+}
+
+// Issuer record of the first issuer of the idendity linked to the token account, and its root issuer record
+// (the records Token-2022 resolves for the transfer hook)
+export async function find_issuer_chain(
+  program: anchor.Program<UndefinedTemporary>,
+  token_account: anchor.web3.PublicKey
+) {
+  const find_pda = (seeds: Buffer[]) =>
+    anchor.web3.PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+  const link = await program.account.identityLink.fetch(
+    find_pda([
+      Buffer.from(anchor.utils.bytes.utf8.encode("identity_link")),
+      token_account.toBuffer(),
+    ])
+  );
+  return find_idendity_issuer_chain(
+    program,
+    find_pda([
+      Buffer.from(anchor.utils.bytes.utf8.encode("identity")),
+      link.subject.toBuffer(),
+    ])
+  );
+}
+
+// Records of the first issuer of an idendity, before any token account is linked to it
+export async function find_idendity_issuer_chain(
+  program: anchor.Program<UndefinedTemporary>,
+  idendity: anchor.web3.PublicKey
+) {
+  const account = await program.account.idAccount.fetch(idendity);
+  const issuer_record = find_issuer_record(program, account.issuers[0].key);
+  const record = await program.account.issuerRecord.fetch(issuer_record);
+  return {
+    issuerRecord: issuer_record,
+    rootIssuerRecord: find_issuer_record(program, record.parent),
+  };
+}
+
+export async function register_issuer(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  issuer_admin: anchor.web3.Keypair,
+  issuer: anchor.web3.PublicKey = args.issuer.publicKey
+) {
+  const issuer_record = find_issuer_record(program, issuer);
+  try {
+    const tx = await program.methods
+      .registerIssuer(issuer)
+      .accounts({
+        issuerRecord: issuer_record,
+        config: get_config(program),
+        issuerAdmin: issuer_admin.publicKey,
+      })
+      .signers([issuer_admin])
+      .rpc();
+
+    console.log("Your transaction signature for register issuer", tx);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  const record = await program.account.issuerRecord.fetch(issuer_record);
+  expect(record.active).to.be.true;
+}
+
+export async function test_grant_role_not_super_admin(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user2 = args.users[1];
+  try {
+    const tx = await program.methods
+      .grantRole({ minterAdmin: {} }, user2.owner.publicKey)
+      .accounts({
+        config: get_config(program),
+        superAdmin: user2.owner.publicKey,
+      })
+      .signers([user2.owner])
+      .rpc();
+
+    console.log("Your transaction signature for grant role", tx);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "NotSuperAdmin"
+    );
+  }
+}
+
+export async function test_super_admin_handover(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user2 = args.users[1];
+  const config_address = get_config(program);
+
+  try {
+    await program.methods
+      .proposeSuperAdmin(user2.owner.publicKey)
+      .accounts({ config: config_address, superAdmin: args.admin.publicKey })
+      .signers([args.admin])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  // Proposing does not transfer anything yet
+  let config = await program.account.programConfig.fetch(config_address);
+  expect(config.superAdmin.toBase58()).to.be.equal(
+    args.admin.publicKey.toBase58()
+  );
+  expect(config.pendingSuperAdmin.toBase58()).to.be.equal(
+    user2.owner.publicKey.toBase58()
+  );
+
+  try {
+    await program.methods
+      .acceptSuperAdmin()
+      .accounts({
+        config: config_address,
+        newSuperAdmin: user2.owner.publicKey,
+      })
+      .signers([user2.owner])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  config = await program.account.programConfig.fetch(config_address);
+  expect(config.superAdmin.toBase58()).to.be.equal(
+    user2.owner.publicKey.toBase58()
+  );
+  expect(config.pendingSuperAdmin).to.be.null;
+
+  // Hand it back so the following tests keep the same admin
+  try {
+    await program.methods
+      .proposeSuperAdmin(args.admin.publicKey)
+      .accounts({ config: config_address, superAdmin: user2.owner.publicKey })
+      .signers([user2.owner])
+      .rpc();
+    await program.methods
+      .acceptSuperAdmin()
+      .accounts({
+        config: config_address,
+        newSuperAdmin: args.admin.publicKey,
+      })
+      .signers([args.admin])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+}
@@ -0,0 +1,152 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  createTransferCheckedWithTransferHookInstruction,
+} from "@solana/spl-token";
+import { sendAndConfirmTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+
+function get_deny_list_entry(
+  program: anchor.Program<UndefinedTemporary>,
+  address: anchor.web3.PublicKey
+) {
+  const [entry] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("deny_list")),
+      address.toBuffer(),
+    ],
+    program.programId
+  );
+  return entry;
+}
+
+// Created once by Compliance, the transfer hook fails until it exists
+export async function init_deny_list(
+  program: anchor.Program<UndefinedTemporary>,
+  compliance: anchor.web3.Keypair
+) {
+  try {
+    await program.methods
+      .initializeDenyList()
+      .accounts({
+        authority: compliance.publicKey,
+      })
+      .signers([compliance])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+}
+
+export async function test_deny_list(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  const other = anchor.web3.Keypair.generate().publicKey;
+  const denied = [user2.owner.publicKey, other];
+
+  try {
+    // The owner is denied: none of its token accounts can receive
+    await program.methods
+      .importDenyList(
+        denied.map((address) => ({ address: address, reasonCode: 1 })),
+        "OFAC-SDN"
+      )
+      .accounts({
+        authority: user1.owner.publicKey,
+      })
+      .remainingAccounts(
+        denied.map((address) => ({
+          pubkey: get_deny_list_entry(program, address),
+          isSigner: false,
+          isWritable: true,
+        }))
+      )
+      .signers([user1.owner])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const entry = await program.account.denyListEntry.fetch(
+    get_deny_list_entry(program, other)
+  );
+  expect(entry.reasonCode).to.be.equal(1);
+  expect(entry.listSource).to.be.equal("OFAC-SDN");
+  const [deny_list] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode("deny_list"))],
+    program.programId
+  );
+  const sorted = denied
+    .map((address) => address.toBase58())
+    .sort((a, b) =>
+      Buffer.compare(
+        new anchor.web3.PublicKey(a).toBuffer(),
+        new anchor.web3.PublicKey(b).toBuffer()
+      )
+    );
+  expect(
+    (await program.account.denyList.fetch(deny_list)).addresses.map(
+      (address) => address.toBase58()
+    )
+  ).to.be.deep.equal(sorted);
+
+  try {
+    let transferInstructionWithHelper =
+      await createTransferCheckedWithTransferHookInstruction(
+        program.provider.connection,
+        user1.token_account,
+        args.mint,
+        user2.token_account,
+        user1.owner.publicKey,
+        BigInt(amount),
+        decimals,
+        [],
+        "confirmed",
+        TOKEN_2022_PROGRAM_ID
+      );
+
+    await sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(transferInstructionWithHelper),
+      [user1.owner]
+    );
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect(
+      ((error as anchor.AnchorError).logs as Array<string>).some((log) =>
+        log.includes("Error Code: AddressDenied.")
+      )
+    ).to.be.true;
+  }
+
+  try {
+    for (const address of denied) {
+      await program.methods
+        .removeFromDenyList(address)
+        .accounts({
+          authority: user1.owner.publicKey,
+          denyListEntry: get_deny_list_entry(program, address),
+        })
+        .signers([user1.owner])
+        .rpc();
+    }
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  expect(
+    await program.provider.connection.getAccountInfo(
+      get_deny_list_entry(program, user2.owner.publicKey)
+    )
+  ).to.be.null;
+  expect((await program.account.denyList.fetch(deny_list)).addresses).to.be
+    .empty;
+}
@@ -0,0 +1,221 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import {
+  find_issuer_chain,
+  find_issuer_record,
+  find_linked_holdings,
+} from "./test_config";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+function find_mint_config(
+  program: anchor.Program<UndefinedTemporary>,
+  mint: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("mint_config")),
+      mint.toBuffer(),
+    ],
+    program.programId
+  )[0];
+}
+
+async function set_max_holders(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  max_holders: anchor.BN
+) {
+  await program.methods
+    .setMaxHolders(max_holders)
+    .accounts({
+      mint: args.mint,
+      superAdmin: args.admin.publicKey,
+    })
+    .signers([args.admin])
+    .rpc();
+}
+
+export async function test_max_holders(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number
+) {
+  const connection = program.provider.connection;
+  const minter = args.users[0].owner;
+  const owner = anchor.web3.Keypair.generate();
+  const token_account = getAssociatedTokenAddressSync(
+    args.mint,
+    owner.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID
+  );
+  const mint_config = find_mint_config(program, args.mint);
+  const mint_to_new_holder = async () =>
+    program.methods
+      .mintTo(new anchor.BN(amount), null)
+      .accounts({
+        mint: args.mint,
+        toTokenAccount: token_account,
+        ...(await find_issuer_chain(program, token_account)),
+        minter: minter.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([minter])
+      .rpc();
+
+  const holders = (await program.account.mintConfig.fetch(mint_config))
+    .holderCount.holders;
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: owner.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    await program.methods
+      .addTokenAccount()
+      .accounts({
+        owner: owner.publicKey,
+        tokenAccount: token_account,
+        mint: args.mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+    await program.methods
+      .addIdendity(new anchor.BN(100000))
+      .accounts({
+        parentIssuerRecord: null,
+        owner: owner.publicKey,
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        mint: args.mint,
+        tokenAccount: token_account,
+        previousHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          token_account
+        ),
+        consent: null,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([args.issuer])
+      .rpc();
+    // No new holder is accepted
+    await set_max_holders(args, program, holders);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    await mint_to_new_holder();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "MaxHoldersReached"
+    );
+  }
+
+  try {
+    await set_max_holders(args, program, new anchor.BN(0));
+    await mint_to_new_holder();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const count = (await program.account.mintConfig.fetch(mint_config))
+    .holderCount;
+  expect(count.holders.toNumber()).to.be.equal(holders.toNumber() + 1);
+  expect(count.maxHolders.toNumber()).to.be.equal(0);
+}
+
+// Every token account of the mint is counted again, sorted by address and a few per transaction
+export async function test_holder_recount(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  const mint_config = find_mint_config(program, args.mint);
+  const token_accounts = (
+    await program.provider.connection.getProgramAccounts(
+      TOKEN_2022_PROGRAM_ID,
+      { filters: [{ memcmp: { offset: 0, bytes: args.mint.toBase58() } }] }
+    )
+  ).sort((a, b) => Buffer.compare(a.pubkey.toBuffer(), b.pubkey.toBuffer()));
+  // The amount is at offset 64 of a token account
+  const holders = token_accounts.filter(
+    (account) => account.account.data.readBigUInt64LE(64) > BigInt(0)
+  ).length;
+  const recount = () =>
+    program.methods.recountHolders().accounts({
+      mint: args.mint,
+      superAdmin: args.admin.publicKey,
+    });
+
+  try {
+    await program.methods
+      .startHolderRecount()
+      .accounts({ mint: args.mint, superAdmin: args.admin.publicKey })
+      .signers([args.admin])
+      .rpc();
+    for (let i = 0; i < token_accounts.length; i += 10) {
+      await recount()
+        .remainingAccounts(
+          token_accounts.slice(i, i + 10).map((account) => ({
+            pubkey: account.pubkey,
+            isSigner: false,
+            isWritable: false,
+          }))
+        )
+        .signers([args.admin])
+        .rpc();
+    }
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    // A token account already counted can't be counted twice
+    await recount()
+      .remainingAccounts([
+        {
+          pubkey: token_accounts[0].pubkey,
+          isSigner: false,
+          isWritable: false,
+        },
+      ])
+      .signers([args.admin])
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "InvalidRecountAccount"
+    );
+  }
+
+  try {
+    await program.methods
+      .finishHolderRecount()
+      .accounts({ mint: args.mint, superAdmin: args.admin.publicKey })
+      .signers([args.admin])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const count = (await program.account.mintConfig.fetch(mint_config))
+    .holderCount;
+  expect(count.holders.toNumber()).to.be.equal(holders);
+  expect(count.recount).to.be.null;
+}
@@ -0,0 +1,1131 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import {
+  find_idendity_issuer_chain,
+  find_issuer_record,
+  find_linked_holdings,
+} from "./test_config";
+import { expect } from "chai";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  createAssociatedTokenAccountIdempotent,
+  getAccount,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+
+async function update_idendity(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  user: number,
+  method: "suspendIdentity" | "reinstateIdentity" | "revokeIdentity",
+  reason_code: number
+) {
+  let user_to = args.users[user];
+  return await program.methods[method](reason_code)
+    .accounts({
+      issuer: args.issuer.publicKey,
+      issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+      idendity: user_to.idendity,
+      parentIssuerRecord: null,
+      subject: user_to.token_account,
+    })
+    .signers([args.issuer])
+    .rpc();
+}
+
+export async function test_suspend_reinstate_renew(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user2 = args.users[1];
+
+  try {
+    await update_idendity(args, program, 1, "suspendIdentity", 7);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  let idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.issuers[0].active).to.be.false;
+  expect(idendity.issuers[0].reasonCode).to.be.equal(7);
+
+  try {
+    await update_idendity(args, program, 1, "reinstateIdentity", 8);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.issuers[0].active).to.be.true;
+  expect(idendity.issuers[0].reasonCode).to.be.equal(8);
+
+  const expires_at = idendity.issuers[0].expiresAt.toNumber();
+  try {
+    await program.methods
+      .renewIdentity(new anchor.BN(100000))
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        idendity: user2.idendity,
+        parentIssuerRecord: null,
+        subject: user2.token_account,
+      })
+      .signers([args.issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.issuers[0].expiresAt.toNumber()).to.be.greaterThan(
+    expires_at
+  );
+}
+
+export async function test_update_idendity_wrong_issuer(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  try {
+    // user1 is not an issuer of this idendity
+    await program.methods
+      .suspendIdentity(1)
+      .accounts({
+        issuer: user1.owner.publicKey,
+        issuerRecord: find_issuer_record(program, user1.owner.publicKey),
+        idendity: user2.idendity,
+        parentIssuerRecord: null,
+        subject: user2.token_account,
+      })
+      .signers([user1.owner])
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect(error).to.be.instanceOf(anchor.AnchorError);
+  }
+}
+
+export async function test_revoke_is_permanent(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user2 = args.users[1];
+  try {
+    await update_idendity(args, program, 1, "revokeIdentity", 9);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.issuers[0].revoked).to.be.true;
+  expect(idendity.issuers[0].active).to.be.false;
+
+  try {
+    await update_idendity(args, program, 1, "reinstateIdentity", 10);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "IdendityRevoked"
+    );
+  }
+}
+
+export async function test_add_remove_issuer(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  second_issuer: anchor.web3.Keypair
+) {
+  let user2 = args.users[1];
+  const connection = program.provider.connection;
+  const size_before = (await connection.getAccountInfo(user2.idendity)).data
+    .length;
+
+  try {
+    await program.methods
+      .addIssuerToIdendity(new anchor.BN(1000))
+      .accounts({
+        issuer: second_issuer.publicKey,
+        issuerRecord: find_issuer_record(program, second_issuer.publicKey),
+        idendity: user2.idendity,
+        parentIssuerRecord: null,
+        owner: user2.owner.publicKey,
+        tokenAccount: user2.token_account,
+        consent: null,
+      })
+      .signers([second_issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  let idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.issuers.length).to.be.equal(2);
+  expect(
+    (await connection.getAccountInfo(user2.idendity)).data.length
+  ).to.be.equal(size_before + 52);
+
+  try {
+    await program.methods
+      .removeIssuerFromIdendity()
+      .accounts({
+        issuer: second_issuer.publicKey,
+        issuerRecord: find_issuer_record(program, second_issuer.publicKey),
+        idendity: user2.idendity,
+        tokenAccount: user2.token_account,
+      })
+      .signers([second_issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.issuers.length).to.be.equal(1);
+  expect(idendity.issuers[0].key.toBase58()).to.be.equal(
+    args.issuer.publicKey.toBase58()
+  );
+  expect(
+    (await connection.getAccountInfo(user2.idendity)).data.length
+  ).to.be.equal(size_before);
+
+  try {
+    // The idendity keeps its last issuer
+    await program.methods
+      .removeIssuerFromIdendity()
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        idendity: user2.idendity,
+        tokenAccount: user2.token_account,
+      })
+      .signers([args.issuer])
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "LastIssuer"
+    );
+  }
+}
+
+async function set_owner_consent_required(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  required: boolean
+) {
+  await program.methods
+    .setOwnerConsentRequired(required)
+    .accounts({
+      mint: args.mint,
+      superAdmin: args.admin.publicKey,
+    })
+    .signers([args.admin])
+    .rpc();
+}
+
+export async function test_owner_consent(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  second_issuer: anchor.web3.Keypair
+) {
+  let user2 = args.users[1];
+  const [consent] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("consent")),
+      user2.token_account.toBuffer(),
+      second_issuer.publicKey.toBuffer(),
+    ],
+    program.programId
+  );
+  const add_issuer = (consent_account: anchor.web3.PublicKey | null) =>
+    program.methods
+      .addIssuerToIdendity(new anchor.BN(1000))
+      .accounts({
+        issuer: second_issuer.publicKey,
+        issuerRecord: find_issuer_record(program, second_issuer.publicKey),
+        idendity: user2.idendity,
+        parentIssuerRecord: null,
+        owner: user2.owner.publicKey,
+        tokenAccount: user2.token_account,
+        consent: consent_account,
+      })
+      .signers([second_issuer])
+      .rpc();
+
+  try {
+    await set_owner_consent_required(args, program, true);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    await add_issuer(null);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "OwnerConsentRequired"
+    );
+  }
+
+  try {
+    await program.methods
+      .grantIssuerConsent(second_issuer.publicKey)
+      .accounts({
+        owner: user2.owner.publicKey,
+        tokenAccount: user2.token_account,
+        consent: consent,
+      })
+      .signers([user2.owner])
+      .rpc();
+    await add_issuer(consent);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  // The consent is consumed by the attestation
+  expect(await program.account.issuerConsent.fetchNullable(consent)).to.be
+    .null;
+  let idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.issuers.length).to.be.equal(2);
+
+  try {
+    await program.methods
+      .detachIssuerFromIdendity()
+      .accounts({
+        owner: user2.owner.publicKey,
+        issuer: second_issuer.publicKey,
+        issuerRecord: find_issuer_record(program, second_issuer.publicKey),
+        idendity: user2.idendity,
+        tokenAccount: user2.token_account,
+      })
+      .signers([user2.owner])
+      .rpc();
+    await set_owner_consent_required(args, program, false);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  idendity = await program.account.idAccount.fetch(user2.idendity);
+  expect(idendity.issuers.length).to.be.equal(1);
+}
+
+export async function test_add_attested_idendity(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user3 = args.users[2];
+  const [issuer_record] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("issuer")),
+      args.issuer.publicKey.toBuffer(),
+    ],
+    program.programId
+  );
+  const now = Math.floor(Date.now() / 1000);
+  const attestation = {
+    mint: args.mint,
+    tokenAccount: user3.token_account,
+    owner: user3.owner.publicKey,
+    expiresAt: new anchor.BN(now + 100000),
+    nonce: new anchor.BN(1),
+    claims: [
+      {
+        claimType: { ageOver18: {} },
+        value: 1,
+        expiresAt: new anchor.BN(now + 100000),
+      },
+    ],
+  };
+  // Signed off-chain by the issuer, the issuer does not sign the transaction
+  const message = program.coder.types.encode(
+    "IdendityAttestation",
+    attestation
+  );
+  const [used_attestation] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("used_attestation")),
+      args.issuer.publicKey.toBuffer(),
+      attestation.nonce.toArrayLike(Buffer, "le", 8),
+    ],
+    program.programId
+  );
+  const add_attested = (signed_message: Buffer) =>
+    program.methods
+      .addAttestedIdendity(attestation)
+      .accounts({
+        owner: user3.owner.publicKey,
+        issuerRecord: issuer_record,
+        idendity: user3.idendity,
+        usedAttestation: used_attestation,
+        parentIssuerRecord: null,
+        mint: args.mint,
+        tokenAccount: user3.token_account,
+        previousHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          user3.token_account
+        ),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .preInstructions([
+        anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+          privateKey: args.issuer.secretKey,
+          message: signed_message,
+        }),
+      ])
+      .signers([user3.owner])
+      .rpc();
+
+  try {
+    // The issuer signed another message
+    await add_attested(Buffer.from("not the attestation"));
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "InvalidEd25519Instruction"
+    );
+  }
+
+  try {
+    await add_attested(message);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const idendity = await program.account.idAccount.fetch(user3.idendity);
+  expect(idendity.issuers[0].key.toBase58()).to.be.equal(
+    args.issuer.publicKey.toBase58()
+  );
+  expect(idendity.claims.length).to.be.equal(1);
+  // The nonce is consumed, the attestation can't be submitted again
+  const used = await program.account.usedAttestation.fetch(used_attestation);
+  expect(used.tokenAccount.toBase58()).to.be.equal(
+    user3.token_account.toBase58()
+  );
+
+  const token_account = await getAccount(
+    program.provider.connection,
+    user3.token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(token_account.isFrozen).to.be.false;
+}
+
+export async function test_sub_issuer(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user2 = args.users[1];
+  const sub_issuer = anchor.web3.Keypair.generate();
+  const record = (key: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("issuer")), key.toBuffer()],
+      program.programId
+    )[0];
+  const root_record = record(args.issuer.publicKey);
+  const sub_record = record(sub_issuer.publicKey);
+  const add_sub_issuer = (duration: number) =>
+    program.methods
+      .addIssuerToIdendity(new anchor.BN(duration))
+      .accounts({
+        issuer: sub_issuer.publicKey,
+        issuerRecord: sub_record,
+        parentIssuerRecord: root_record,
+        idendity: user2.idendity,
+        owner: user2.owner.publicKey,
+        tokenAccount: user2.token_account,
+        consent: null,
+      })
+      .signers([sub_issuer])
+      .rpc();
+
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: sub_issuer.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    // Country claims in US only, for at most 5000 seconds
+    await program.methods
+      .registerSubIssuer(
+        sub_issuer.publicKey,
+        {
+          claimTypes: 1,
+          maxValidityDuration: new anchor.BN(5000),
+          jurisdictions: [840],
+        },
+        new anchor.BN(Math.floor(Date.now() / 1000) + 100000)
+      )
+      .accounts({
+        subIssuerRecord: sub_record,
+        rootIssuerRecord: root_record,
+        rootIssuer: args.issuer.publicKey,
+      })
+      .signers([args.issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    await add_sub_issuer(10000);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "OutOfIssuerScope"
+    );
+  }
+
+  try {
+    await add_sub_issuer(1000);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    await program.methods
+      .setClaim({ country: {} }, 250, new anchor.BN(1000))
+      .accounts({
+        issuer: sub_issuer.publicKey,
+        issuerRecord: sub_record,
+        parentIssuerRecord: root_record,
+        idendity: user2.idendity,
+        subject: user2.token_account,
+      })
+      .signers([sub_issuer])
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "OutOfIssuerScope"
+    );
+  }
+
+  try {
+    await program.methods
+      .removeIssuerFromIdendity()
+      .accounts({
+        issuer: sub_issuer.publicKey,
+        issuerRecord: find_issuer_record(program, sub_issuer.publicKey),
+        idendity: user2.idendity,
+        tokenAccount: user2.token_account,
+      })
+      .signers([sub_issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+}
+
+export async function test_rotate_issuer_key(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  issuer: anchor.web3.Keypair
+) {
+  let user2 = args.users[1];
+  const new_key = anchor.web3.Keypair.generate();
+  const issuer_record = find_issuer_record(program, issuer.publicKey);
+  const add_issuer = (signer: anchor.web3.Keypair) =>
+    program.methods
+      .addIssuerToIdendity(new anchor.BN(1000))
+      .accounts({
+        issuer: signer.publicKey,
+        issuerRecord: issuer_record,
+        idendity: user2.idendity,
+        parentIssuerRecord: null,
+        owner: user2.owner.publicKey,
+        tokenAccount: user2.token_account,
+        consent: null,
+      })
+      .signers([signer])
+      .rpc();
+
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: new_key.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    await program.methods
+      .rotateIssuerKey()
+      .accounts({
+        issuerRecord: issuer_record,
+        issuer: issuer.publicKey,
+        newKey: new_key.publicKey,
+      })
+      .signers([issuer, new_key])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const record = await program.account.issuerRecord.fetch(issuer_record);
+  expect(record.key.toBase58()).to.be.equal(new_key.publicKey.toBase58());
+  expect(record.previousKeys[0].toBase58()).to.be.equal(
+    issuer.publicKey.toBase58()
+  );
+
+  try {
+    // The previous key can no longer sign for the issuer
+    await add_issuer(issuer);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "NotIssuerKey"
+    );
+  }
+
+  try {
+    await add_issuer(new_key);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  // The idendity references the stable issuer id, not the signing key
+  const idendity = await program.account.idAccount.fetch(user2.idendity);
+  const added = idendity.issuers[idendity.issuers.length - 1];
+  expect(added.key.toBase58()).to.be.equal(issuer.publicKey.toBase58());
+
+  try {
+    await program.methods
+      .removeIssuerFromIdendity()
+      .accounts({
+        issuer: new_key.publicKey,
+        issuerRecord: issuer_record,
+        idendity: user2.idendity,
+        tokenAccount: user2.token_account,
+      })
+      .signers([new_key])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+}
+
+async function set_validity_bounds(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  min: number,
+  max: number
+) {
+  await program.methods
+    .setValidityBounds(new anchor.BN(min), new anchor.BN(max))
+    .accounts({
+      mint: args.mint,
+      superAdmin: args.admin.publicKey,
+    })
+    .signers([args.admin])
+    .rpc();
+}
+
+export async function test_validity_duration_limits(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  let user2 = args.users[1];
+  const renew = (duration: anchor.BN) =>
+    program.methods
+      .renewIdentity(duration)
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        idendity: user2.idendity,
+        parentIssuerRecord: null,
+        subject: user2.token_account,
+      })
+      .signers([args.issuer])
+      .rpc();
+  const expect_error = async (duration: anchor.BN, code: string) => {
+    try {
+      await renew(duration);
+      expect.fail("This test should fail");
+    } catch (error) {
+      expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+        code
+      );
+    }
+  };
+
+  await expect_error(new anchor.BN(-1), "NonPositiveValidityDuration");
+  // i64::MAX overflows the expiration timestamp
+  await expect_error(
+    new anchor.BN("9223372036854775807"),
+    "ValidityDurationOverflow"
+  );
+
+  try {
+    await set_validity_bounds(args, program, 100, 10000);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  await expect_error(new anchor.BN(50), "BelowMintMinValidity");
+  await expect_error(new anchor.BN(20000), "AboveMintMaxValidity");
+
+  try {
+    await renew(new anchor.BN(5000));
+    await set_validity_bounds(args, program, 0, 0);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+}
+
+export async function test_person_idendity(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  const connection = program.provider.connection;
+  const owner = anchor.web3.Keypair.generate();
+  const token_account = getAssociatedTokenAddressSync(
+    args.mint,
+    owner.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID
+  );
+  const [idendity] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("identity")),
+      owner.publicKey.toBuffer(),
+    ],
+    program.programId
+  );
+  const [link] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("identity_link")),
+      token_account.toBuffer(),
+    ],
+    program.programId
+  );
+  const link_token_account = async () =>
+    program.methods
+      .linkTokenAccount()
+      .accounts({
+        owner: owner.publicKey,
+        idendity: idendity,
+        link: link,
+        mint: args.mint,
+        tokenAccount: token_account,
+        previousHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          token_account
+        ),
+        ...(await find_idendity_issuer_chain(program, idendity)),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+  const update_person = (method: "suspendIdentity" | "reinstateIdentity") =>
+    program.methods[method](1)
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        idendity: idendity,
+        parentIssuerRecord: null,
+        subject: owner.publicKey,
+      })
+      .signers([args.issuer])
+      .rpc();
+
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: owner.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    await program.methods
+      .addTokenAccount()
+      .accounts({
+        owner: owner.publicKey,
+        tokenAccount: token_account,
+        mint: args.mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+    // One idendity for the person, not for a token account
+    await program.methods
+      .addPersonIdentity(new anchor.BN(100000))
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        parentIssuerRecord: null,
+        idendity: idendity,
+        owner: owner.publicKey,
+      })
+      .signers([args.issuer, owner])
+      .rpc();
+    await update_person("suspendIdentity");
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    // A suspended idendity does not unlock the token account
+    await link_token_account();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "IdendityNotActive"
+    );
+  }
+  const suspended = await getAccount(
+    connection,
+    token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(suspended.isFrozen).to.be.true;
+
+  try {
+    await update_person("reinstateIdentity");
+    await link_token_account();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const person = await program.account.idAccount.fetch(idendity);
+  expect(person.owner.toBase58()).to.be.equal(owner.publicKey.toBase58());
+  expect(person.tokenAccount.toBase58()).to.be.equal(
+    anchor.web3.PublicKey.default.toBase58()
+  );
+  const identity_link = await program.account.identityLink.fetch(link);
+  expect(identity_link.subject.toBase58()).to.be.equal(
+    owner.publicKey.toBase58()
+  );
+  // The first link unlocks the token account
+  const account = await getAccount(
+    connection,
+    token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(account.isFrozen).to.be.false;
+
+  try {
+    await program.methods
+      .unlinkTokenAccount()
+      .accounts({
+        owner: owner.publicKey,
+        link: link,
+        tokenAccount: token_account,
+      })
+      .signers([owner])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  expect(await connection.getAccountInfo(link)).to.be.null;
+
+  // A freeze ordered by Compliance is not lifted by linking the token account again
+  const compliance = args.users[0].owner;
+  try {
+    await program.methods
+      .freezeAccount(3, "CASE-2024-003")
+      .accounts({
+        authority: compliance.publicKey,
+        mint: args.mint,
+        tokenAccount: token_account,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([compliance])
+      .rpc();
+    await link_token_account();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const frozen = await getAccount(
+    connection,
+    token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(frozen.isFrozen).to.be.true;
+}
+
+export async function test_institution_idendity(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  const connection = program.provider.connection;
+  const entity = anchor.web3.Keypair.generate();
+  const signer = anchor.web3.Keypair.generate();
+  const find_pda = (seed: string, key: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(anchor.utils.bytes.utf8.encode(seed)), key.toBuffer()],
+      program.programId
+    )[0];
+  const idendity = find_pda("identity", entity.publicKey);
+  const institution = find_pda("institution", entity.publicKey);
+  const signer_token_account = getAssociatedTokenAddressSync(
+    args.mint,
+    signer.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID
+  );
+  // A vault owned by a PDA of a program, here this program
+  const [vault_owner, vault_bump] =
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(anchor.utils.bytes.utf8.encode("vault"))],
+      program.programId
+    );
+  const vault = getAssociatedTokenAddressSync(
+    args.mint,
+    vault_owner,
+    true,
+    TOKEN_2022_PROGRAM_ID
+  );
+  const link_vault = async (seeds: Buffer[]) =>
+    program.methods
+      .linkProgramVault(seeds)
+      .accounts({
+        entity: entity.publicKey,
+        vaultOwner: vault_owner,
+        institution: institution,
+        idendity: idendity,
+        link: find_pda("identity_link", vault),
+        vaultProgram: program.programId,
+        mint: args.mint,
+        tokenAccount: vault,
+        lastTx: find_pda("last_tx", vault_owner),
+        twoAuth: find_pda("two_auth", vault),
+        transactionApproval: find_pda("transaction_approval", vault_owner),
+        ...(await find_idendity_issuer_chain(program, idendity)),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([entity])
+      .rpc();
+  const link_institution_token_account = async () =>
+    program.methods
+      .linkInstitutionTokenAccount()
+      .accounts({
+        signer: signer.publicKey,
+        institution: institution,
+        idendity: idendity,
+        link: find_pda("identity_link", signer_token_account),
+        mint: args.mint,
+        tokenAccount: signer_token_account,
+        previousHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          signer_token_account
+        ),
+        ...(await find_idendity_issuer_chain(program, idendity)),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([signer])
+      .rpc();
+  const update_institution = (
+    method: "suspendIdentity" | "reinstateIdentity"
+  ) =>
+    program.methods[method](1)
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        idendity: idendity,
+        parentIssuerRecord: null,
+        subject: entity.publicKey,
+      })
+      .signers([args.issuer])
+      .rpc();
+
+  try {
+    for (const key of [entity.publicKey, signer.publicKey]) {
+      await anchor.web3.sendAndConfirmTransaction(
+        connection,
+        new anchor.web3.Transaction().add(
+          anchor.web3.SystemProgram.transfer({
+            fromPubkey: args.admin.publicKey,
+            toPubkey: key,
+            lamports: 0.1 * anchor.web3.LAMPORTS_PER_SOL,
+          })
+        ),
+        [args.admin]
+      );
+    }
+    await program.methods
+      .addInstitutionIdentity(new anchor.BN(100000), [signer.publicKey])
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        parentIssuerRecord: null,
+        idendity: idendity,
+        institution: institution,
+        entity: entity.publicKey,
+      })
+      .signers([args.issuer, entity])
+      .rpc();
+    await program.methods
+      .addTokenAccount()
+      .accounts({
+        owner: signer.publicKey,
+        tokenAccount: signer_token_account,
+        mint: args.mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([signer])
+      .rpc();
+    await update_institution("suspendIdentity");
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    // A suspended institution does not unlock the token accounts of its signers
+    await link_institution_token_account();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "IdendityNotActive"
+    );
+  }
+  const suspended = await getAccount(
+    connection,
+    signer_token_account,
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  expect(suspended.isFrozen).to.be.true;
+
+  try {
+    await update_institution("reinstateIdentity");
+    // The signer links its token account to its own person idendity first
+    await program.methods
+      .addPersonIdentity(new anchor.BN(100000))
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        parentIssuerRecord: null,
+        idendity: find_pda("identity", signer.publicKey),
+        owner: signer.publicKey,
+      })
+      .signers([args.issuer, signer])
+      .rpc();
+    await program.methods
+      .linkTokenAccount()
+      .accounts({
+        owner: signer.publicKey,
+        idendity: find_pda("identity", signer.publicKey),
+        mint: args.mint,
+        tokenAccount: signer_token_account,
+        previousHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          signer_token_account
+        ),
+        ...(await find_idendity_issuer_chain(
+          program,
+          find_pda("identity", signer.publicKey)
+        )),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([signer])
+      .rpc();
+    await link_institution_token_account();
+    await createAssociatedTokenAccountIdempotent(
+      connection,
+      entity,
+      args.mint,
+      vault_owner,
+      undefined,
+      TOKEN_2022_PROGRAM_ID,
+      undefined,
+      true
+    );
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const signer_link = await program.account.identityLink.fetch(
+    find_pda("identity_link", signer_token_account)
+  );
+  expect(signer_link.subject.toBase58()).to.be.equal(
+    entity.publicKey.toBase58()
+  );
+  expect(signer_link.owner.toBase58()).to.be.equal(
+    signer.publicKey.toBase58()
+  );
+
+  try {
+    // The token account moved to the institution, its owner cannot unlink it from its person idendity anymore
+    await program.methods
+      .unlinkTokenAccount()
+      .accounts({
+        owner: signer.publicKey,
+        link: find_pda("identity_link", signer_token_account),
+        tokenAccount: signer_token_account,
+      })
+      .signers([signer])
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "NotPersonLink"
+    );
+  }
+
+  try {
+    // Only the vault program can sign for the vault owner, in a CPI
+    await link_vault([
+      Buffer.from(anchor.utils.bytes.utf8.encode("vault")),
+      Buffer.from([vault_bump]),
+    ]);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as Error).message).to.include(
+      "Signature verification failed"
+    );
+  }
+  const registered = await program.account.institution.fetch(institution);
+  expect(registered.vaults).to.be.empty;
+
+  try {
+    // The signer leaves the institution, its token account is not covered anymore
+    await program.methods
+      .setAuthorizedSigners([])
+      .accounts({
+        entity: entity.publicKey,
+        institution: institution,
+      })
+      .signers([entity])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  expect(
+    (await program.account.institution.fetch(institution)).authorizedSigners
+  ).to.be.empty;
+}
@@ -7,18 +7,18 @@ export async function init_mint(
   args: AccountArgs,
   program: anchor.Program<UndefinedTemporary>
 ) {
-  let user1 = args.users[0];
+  let admin = args.admin;
   let mint = args.mint;
   try {
     const tx = await program.methods
       .initializeTokenMint()
       .accounts({
-        payer: user1.owner.publicKey,
+        payer: admin.publicKey,
         mint: mint,
         systemProgram: anchor.web3.SystemProgram.programId,
         tokenProgram: TOKEN_2022_PROGRAM_ID,
       })
-      .signers([user1.owner])
+      .signers([admin])
       .rpc({ skipPreflight: true });
 
     console.log("Your transaction signature for initialized token mint", tx);
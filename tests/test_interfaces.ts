@@ -12,6 +12,7 @@ interface AssociatedTokenAccounts {
 interface AccountArgs {
   users: AssociatedTokenAccounts[];
   issuer: anchor.web3.Keypair;
+  admin: anchor.web3.Keypair;
   mint: anchor.web3.PublicKey;
 }
 
@@ -0,0 +1,131 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  createTransferCheckedWithTransferHookInstruction,
+} from "@solana/spl-token";
+import { sendAndConfirmTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+
+async function expect_transfer_error(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number,
+  error_code: string
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  try {
+    let transferInstructionWithHelper =
+      await createTransferCheckedWithTransferHookInstruction(
+        program.provider.connection,
+        user1.token_account,
+        args.mint,
+        user2.token_account,
+        user1.owner.publicKey,
+        BigInt(amount),
+        decimals,
+        [],
+        "confirmed",
+        TOKEN_2022_PROGRAM_ID
+      );
+
+    const txSig = await sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(transferInstructionWithHelper),
+      [user1.owner]
+    );
+    console.log("Transfer Signature: NOT NORMAL", txSig);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect(
+      ((error as anchor.AnchorError).logs as Array<string>).some((log) =>
+        log.includes(`Error Code: ${error_code}.`)
+      )
+    ).to.be.true;
+  }
+}
+
+export async function test_jurisdiction_matrix(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number
+) {
+  // user1 has the compliance role, user2 has a country claim (840)
+  let compliance = args.users[0].owner;
+  const accounts = { mint: args.mint, compliance: compliance.publicKey };
+
+  try {
+    await program.methods
+      .addJurisdiction(840)
+      .accounts(accounts)
+      .signers([compliance])
+      .rpc();
+    await program.methods
+      .setJurisdictionSanctioned(840, true)
+      .accounts(accounts)
+      .signers([compliance])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  await expect_transfer_error(
+    args,
+    program,
+    amount,
+    decimals,
+    "SanctionedJurisdiction"
+  );
+
+  try {
+    await program.methods
+      .setJurisdictionSanctioned(840, false)
+      .accounts(accounts)
+      .signers([compliance])
+      .rpc();
+    await program.methods
+      .setBlockUnlistedJurisdictions(true)
+      .accounts(accounts)
+      .signers([compliance])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  // user1 has no country claim
+  await expect_transfer_error(
+    args,
+    program,
+    amount,
+    decimals,
+    "JurisdictionNotAllowed"
+  );
+
+  try {
+    await program.methods
+      .setBlockUnlistedJurisdictions(false)
+      .accounts(accounts)
+      .signers([compliance])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    await program.methods
+      .addJurisdiction(840)
+      .accounts(accounts)
+      .signers([compliance])
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "JurisdictionAlreadyListed"
+    );
+  }
+}
@@ -0,0 +1,177 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs, AssociatedTokenAccounts } from "./test_interfaces";
+import { find_issuer_chain } from "./test_config";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  createTransferCheckedWithTransferHookInstruction,
+  getAccount,
+} from "@solana/spl-token";
+import { sendAndConfirmTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+
+async function transfer(
+  program: anchor.Program<UndefinedTemporary>,
+  from: AssociatedTokenAccounts,
+  to: AssociatedTokenAccounts,
+  mint: anchor.web3.PublicKey,
+  amount: bigint,
+  decimals: number
+) {
+  let transferInstructionWithHelper =
+    await createTransferCheckedWithTransferHookInstruction(
+      program.provider.connection,
+      from.token_account,
+      mint,
+      to.token_account,
+      from.owner.publicKey,
+      amount,
+      decimals,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+
+  return await sendAndConfirmTransaction(
+    program.provider.connection,
+    new anchor.web3.Transaction().add(transferInstructionWithHelper),
+    [from.owner]
+  );
+}
+
+function find_lockup(
+  program: anchor.Program<UndefinedTemporary>,
+  token_account: anchor.web3.PublicKey
+) {
+  return anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("lockup"), token_account.toBuffer()],
+    program.programId
+  )[0];
+}
+
+// The minted tokens are locked on user2, who first tries to send more than its unlocked tokens to user1
+export async function test_lockup(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  const lockup = find_lockup(program, user2.token_account);
+  const unlocked = (
+    await getAccount(
+      program.provider.connection,
+      user2.token_account,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    )
+  ).amount;
+  const unlock = Math.floor(Date.now() / 1000) + 5;
+  const mint_locked = async (cliff: number) =>
+    program.methods
+      .mintTo(new anchor.BN(amount), {
+        start: new anchor.BN(cliff),
+        cliff: new anchor.BN(cliff),
+        end: new anchor.BN(unlock),
+      })
+      .accounts({
+        mint: args.mint,
+        toTokenAccount: user2.token_account,
+        ...(await find_issuer_chain(program, user2.token_account)),
+        minter: user1.owner.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user1.owner])
+      .rpc();
+
+  try {
+    // The minted tokens are locked for a few seconds
+    await mint_locked(unlock);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const created = await program.account.lockup.fetch(lockup);
+  expect(created.amount.toNumber()).to.be.equal(amount);
+  expect(created.authority.toBase58()).to.be.equal(
+    user1.owner.publicKey.toBase58()
+  );
+  expect(created.payer.toBase58()).to.be.equal(
+    user1.owner.publicKey.toBase58()
+  );
+
+  try {
+    await transfer(
+      program,
+      user2,
+      user1,
+      args.mint,
+      unlocked + BigInt(1),
+      decimals
+    );
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect(
+      ((error as anchor.AnchorError).logs as Array<string>).some((log) =>
+        log.includes("Error Code: TokensLocked.")
+      )
+    ).to.be.true;
+  }
+
+  try {
+    // An extension can't release the locked tokens earlier
+    await mint_locked(unlock - 1);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "LockupShortened"
+    );
+  }
+
+  try {
+    await program.methods
+      .closeLockup()
+      .accounts({
+        lockup: lockup,
+        payer: user1.owner.publicKey,
+      })
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "LockupNotReleased"
+    );
+  }
+
+  await new Promise((resolve) => setTimeout(resolve, 7000));
+
+  try {
+    await transfer(
+      program,
+      user2,
+      user1,
+      args.mint,
+      unlocked + BigInt(1),
+      decimals
+    );
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  // The released lockup is closed, the next locked mint creates a new one
+  try {
+    await program.methods
+      .closeLockup()
+      .accounts({
+        lockup: lockup,
+        payer: user1.owner.publicKey,
+      })
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  expect(await program.provider.connection.getAccountInfo(lockup)).to.be.null;
+}
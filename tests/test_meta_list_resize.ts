@@ -0,0 +1,162 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+} from "@solana/spl-token";
+import { startAnchor, ProgramTestContext } from "solana-bankrun";
+import { BankrunProvider } from "anchor-bankrun";
+import { createHash } from "crypto";
+import { expect } from "chai";
+
+const IDL = require("../target/idl/undefined_temporary.json");
+
+// TLV entry of the ExtraAccountMetaList: discriminator (8), length (4), then the meta count (4) and 35 bytes per meta
+const META_SIZE = 35;
+const HEADER_SIZE = 8 + 4 + 4;
+
+// The meta list of a mint created by an older (or newer) program version, with the given number of metas
+function meta_list_data(count: number): Buffer {
+  const data = Buffer.alloc(HEADER_SIZE + META_SIZE * count);
+  createHash("sha256")
+    .update("spl-transfer-hook-interface:execute")
+    .digest()
+    .copy(data, 0, 0, 8);
+  data.writeUInt32LE(4 + META_SIZE * count, 8);
+  data.writeUInt32LE(count, 12);
+  return data;
+}
+
+// The layout can only change with a program upgrade: bankrun writes the list of another version in place
+// of the current one, update_extra_account_meta_list must then grow or shrink it and settle the rent with the payer.
+describe("ExtraAccountMetaList resize", () => {
+  let context: ProgramTestContext;
+  let program: anchor.Program<UndefinedTemporary>;
+  let payer: anchor.web3.Keypair;
+  let mint: anchor.web3.PublicKey;
+  let mint_config: anchor.web3.PublicKey;
+  let extra_account_meta_list: anchor.web3.PublicKey;
+
+  const set_meta_list = async (count: number) => {
+    const data = meta_list_data(count);
+    const rent = await context.banksClient.getRent();
+    context.setAccount(extra_account_meta_list, {
+      lamports: Number(rent.minimumBalance(BigInt(data.length))),
+      data: data,
+      owner: program.programId,
+      executable: false,
+    });
+  };
+
+  // Returns the lamports received by the payer (negative when it paid), transaction fee excluded
+  const update_meta_list = async () => {
+    // A new blockhash, the update transactions are otherwise identical
+    context.warpToSlot((await context.banksClient.getSlot()) + BigInt(1));
+    const before = await context.banksClient.getBalance(payer.publicKey);
+    await program.methods
+      .updateExtraAccountMetaList()
+      .accounts({
+        payer: payer.publicKey,
+        extraAccountMetaList: extra_account_meta_list,
+        mint: mint,
+        mintConfig: mint_config,
+      })
+      .rpc();
+    const after = await context.banksClient.getBalance(payer.publicKey);
+    return Number(after - before) + 5000;
+  };
+
+  const meta_list_account = async () => {
+    const account = await context.banksClient.getAccount(
+      extra_account_meta_list
+    );
+    const rent = await context.banksClient.getRent();
+    return {
+      size: account.data.length,
+      lamports: account.lamports,
+      rent_exempt: Number(rent.minimumBalance(BigInt(account.data.length))),
+    };
+  };
+
+  before(async () => {
+    context = await startAnchor(".", [], []);
+    const provider = new BankrunProvider(context);
+    program = new anchor.Program<UndefinedTemporary>(IDL, provider);
+    payer = context.payer;
+
+    const find_pda = (seeds: Buffer[]) =>
+      anchor.web3.PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+    mint = find_pda([Buffer.from("mint")]);
+    mint_config = find_pda([Buffer.from("mint_config"), mint.toBuffer()]);
+    extra_account_meta_list = find_pda([
+      Buffer.from("extra-account-metas"),
+      mint.toBuffer(),
+    ]);
+
+    // Bankrun deploys the program without program data, the config is written directly
+    const config = await program.coder.accounts.encode("ProgramConfig", {
+      superAdmin: payer.publicKey,
+      pendingSuperAdmin: null,
+      roles: [],
+      paused: false,
+    });
+    context.setAccount(find_pda([Buffer.from("config")]), {
+      lamports: anchor.web3.LAMPORTS_PER_SOL,
+      data: config,
+      owner: program.programId,
+      executable: false,
+    });
+
+    await program.methods
+      .initializeTokenMint()
+      .accounts({
+        payer: payer.publicKey,
+        mint: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .rpc();
+    await program.methods
+      .initializeExtraAccountMetaList()
+      .accounts({
+        payer: payer.publicKey,
+        extraAccountMetaList: extra_account_meta_list,
+        mint: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+  });
+
+  it("Grows the list and takes the missing rent from the payer", async () => {
+    const current = await meta_list_account();
+    await set_meta_list(1);
+    const small = await meta_list_account();
+
+    const received = await update_meta_list();
+
+    const grown = await meta_list_account();
+    expect(grown.size).to.be.equal(current.size);
+    expect(grown.lamports).to.be.equal(grown.rent_exempt);
+    expect(received).to.be.equal(small.lamports - grown.lamports);
+  });
+
+  it("Shrinks the list and refunds the excess rent to the payer", async () => {
+    const current = await meta_list_account();
+    const count = (current.size - HEADER_SIZE) / META_SIZE;
+    await set_meta_list(count + 5);
+    const large = await meta_list_account();
+
+    const received = await update_meta_list();
+
+    const shrunk = await meta_list_account();
+    expect(shrunk.size).to.be.equal(current.size);
+    expect(shrunk.lamports).to.be.equal(shrunk.rent_exempt);
+    expect(received).to.be.equal(large.lamports - shrunk.lamports);
+
+    const version = program.idl.constants.find(
+      (constant) => constant.name === "EXTRA_ACCOUNT_METAS_VERSION"
+    ).value;
+    const config = await program.account.mintConfig.fetch(mint_config);
+    expect(config.extraAccountMetasVersion).to.be.equal(Number(version));
+  });
+});
@@ -0,0 +1,173 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { TOKEN_2022_PROGRAM_ID } from "@solana/spl-token";
+import { startAnchor, ProgramTestContext } from "solana-bankrun";
+import { BankrunProvider } from "anchor-bankrun";
+import { createHash } from "crypto";
+import { expect } from "chai";
+
+const IDL = require("../target/idl/undefined_temporary.json");
+
+// MintConfig before the balance caps and the holder count were added to it: paused, pause_exemptions,
+// extra_account_metas_version, owner_consent_required, required_claims, min and max validity durations (see LegacyMintConfig)
+const LEGACY_MINT_CONFIG_SIZE = 8 + 1 + 4 + 32 * 10 + 2 + 1 + 4 + 5 * 8 + 8 + 8;
+
+function legacy_mint_config_data(paused: boolean, version: number): Buffer {
+  const data = Buffer.alloc(LEGACY_MINT_CONFIG_SIZE);
+  createHash("sha256")
+    .update("account:MintConfig")
+    .digest()
+    .copy(data, 0, 0, 8);
+  let offset = 8;
+  data.writeUInt8(paused ? 1 : 0, offset);
+  offset += 1 + 4; // no pause exemption
+  data.writeUInt16LE(version, offset);
+  offset += 2;
+  data.writeUInt8(1, offset); // owner consent required
+  return data;
+}
+
+// The accounts of a mint created by an older program version are written by bankrun, the migrations must bring them
+// to the current layout without losing their settings and create the ones it lacks.
+describe("Migrations of an existing mint", () => {
+  let context: ProgramTestContext;
+  let program: anchor.Program<UndefinedTemporary>;
+  let payer: anchor.web3.Keypair;
+  let mint: anchor.web3.PublicKey;
+  let mint_config: anchor.web3.PublicKey;
+
+  before(async () => {
+    context = await startAnchor(".", [], []);
+    const provider = new BankrunProvider(context);
+    program = new anchor.Program<UndefinedTemporary>(IDL, provider);
+    payer = context.payer;
+
+    const find_pda = (seeds: Buffer[]) =>
+      anchor.web3.PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+    mint = find_pda([Buffer.from("mint")]);
+    mint_config = find_pda([Buffer.from("mint_config"), mint.toBuffer()]);
+
+    // Bankrun deploys the program without program data, the config is written directly
+    const config = await program.coder.accounts.encode("ProgramConfig", {
+      superAdmin: payer.publicKey,
+      pendingSuperAdmin: null,
+      roles: [],
+      paused: false,
+    });
+    context.setAccount(find_pda([Buffer.from("config")]), {
+      lamports: anchor.web3.LAMPORTS_PER_SOL,
+      data: config,
+      owner: program.programId,
+      executable: false,
+    });
+
+    await program.methods
+      .initializeTokenMint()
+      .accounts({
+        payer: payer.publicKey,
+        mint: mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .rpc();
+  });
+
+  it("Grows a mint config of the legacy layout and keeps its settings", async () => {
+    const current_size = (await context.banksClient.getAccount(mint_config))
+      .data.length;
+    const data = legacy_mint_config_data(true, 7);
+    const rent = await context.banksClient.getRent();
+    context.setAccount(mint_config, {
+      lamports: Number(rent.minimumBalance(BigInt(data.length))),
+      data: data,
+      owner: program.programId,
+      executable: false,
+    });
+
+    await program.methods
+      .migrateMintConfig()
+      .accounts({
+        payer: payer.publicKey,
+        mint: mint,
+      })
+      .rpc();
+
+    const account = await context.banksClient.getAccount(mint_config);
+    expect(account.data.length).to.be.equal(current_size);
+    expect(account.lamports).to.be.equal(
+      Number(rent.minimumBalance(BigInt(current_size)))
+    );
+    const config = await program.account.mintConfig.fetch(mint_config);
+    expect(config.paused).to.be.true;
+    expect(config.extraAccountMetasVersion).to.be.equal(7);
+    expect(config.ownerConsentRequired).to.be.true;
+    expect(config.balanceCaps.defaultMaxBalance.toString()).to.be.equal(
+      "18446744073709551615"
+    );
+    expect(config.balanceCaps.tiers).to.be.empty;
+    // Seeded by a recount of the holders
+    expect(config.holderCount.holders.toNumber()).to.be.equal(0);
+    expect(config.holderCount.recount).to.be.null;
+  });
+
+  it("Does not migrate a mint config of the current layout", async () => {
+    context.warpToSlot((await context.banksClient.getSlot()) + BigInt(1));
+    try {
+      await program.methods
+        .migrateMintConfig()
+        .accounts({
+          payer: payer.publicKey,
+          mint: mint,
+        })
+        .rpc();
+      expect.fail("This test should fail");
+    } catch (error) {
+      expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+        "MintConfigAlreadyMigrated"
+      );
+    }
+  });
+
+  it("Creates the jurisdiction matrix of a mint created without it", async () => {
+    const jurisdiction_matrix = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("jurisdiction"), mint.toBuffer()],
+      program.programId
+    )[0];
+    context.setAccount(jurisdiction_matrix, {
+      lamports: 0,
+      data: Buffer.alloc(0),
+      owner: anchor.web3.SystemProgram.programId,
+      executable: false,
+    });
+
+    await program.methods
+      .initializeJurisdictionMatrix()
+      .accounts({
+        payer: payer.publicKey,
+        mint: mint,
+      })
+      .rpc();
+
+    const matrix = await program.account.jurisdictionMatrix.fetch(
+      jurisdiction_matrix
+    );
+    expect(matrix.blockUnlisted).to.be.false;
+    expect(matrix.countries).to.be.empty;
+    expect(matrix.blockedPairs).to.be.empty;
+  });
+
+  it("Does not create the jurisdiction matrix twice", async () => {
+    context.warpToSlot((await context.banksClient.getSlot()) + BigInt(1));
+    try {
+      await program.methods
+        .initializeJurisdictionMatrix()
+        .accounts({
+          payer: payer.publicKey,
+          mint: mint,
+        })
+        .rpc();
+      expect.fail("This test should fail");
+    } catch (error) {
+      expect(error.toString()).to.not.contain("This test should fail");
+    }
+  });
+});
@@ -0,0 +1,99 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import { find_issuer_chain } from "./test_config";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  createTransferCheckedWithTransferHookInstruction,
+} from "@solana/spl-token";
+import { sendAndConfirmTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+
+async function set_mint_paused(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  paused: boolean
+) {
+  const tx = await program.methods
+    .setMintPaused(paused)
+    .accounts({
+      mint: args.mint,
+      pauser: args.users[0].owner.publicKey,
+    })
+    .signers([args.users[0].owner])
+    .rpc();
+  console.log("Your transaction signature for set mint paused", tx);
+}
+
+export async function test_paused_transfer(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+
+  try {
+    await set_mint_paused(args, program, true);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    let transferInstructionWithHelper =
+      await createTransferCheckedWithTransferHookInstruction(
+        program.provider.connection,
+        user1.token_account,
+        args.mint,
+        user2.token_account,
+        user1.owner.publicKey,
+        BigInt(amount),
+        decimals,
+        [],
+        "confirmed",
+        TOKEN_2022_PROGRAM_ID
+      );
+
+    const txSig = await sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(transferInstructionWithHelper),
+      [user1.owner]
+    );
+    console.log("Transfer Signature: NOT NORMAL", txSig);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect(
+      ((error as anchor.AnchorError).logs as Array<string>).some((log) =>
+        log.includes("Error Code: Paused.")
+      )
+    ).to.be.true;
+  }
+
+  try {
+    await program.methods
+      .mintTo(new anchor.BN(amount), null)
+      .accounts({
+        mint: args.mint,
+        toTokenAccount: user1.token_account,
+        ...(await find_issuer_chain(program, user1.token_account)),
+        minter: user1.owner.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user1.owner])
+      .rpc();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "Paused"
+    );
+  }
+
+  try {
+    await set_mint_paused(args, program, false);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+}
@@ -0,0 +1,372 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import {
+  find_idendity_issuer_chain,
+  find_issuer_chain,
+  find_issuer_record,
+  find_linked_holdings,
+} from "./test_config";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import { expect } from "chai";
+
+export async function test_prune_expired_idendity(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  const connection = program.provider.connection;
+  const owner = anchor.web3.Keypair.generate();
+  const cranker = args.admin;
+  const token_account = getAssociatedTokenAddressSync(
+    args.mint,
+    owner.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID
+  );
+  const [idendity] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("identity")),
+      token_account.toBuffer(),
+    ],
+    program.programId
+  );
+  const update_idendity = (method: "suspendIdentity" | "reinstateIdentity") =>
+    program.methods[method](1)
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        idendity: idendity,
+        parentIssuerRecord: null,
+        subject: token_account,
+      })
+      .signers([args.issuer])
+      .rpc();
+  const prune = (payer: anchor.web3.PublicKey) =>
+    program.methods
+      .pruneExpiredIdentity()
+      .accounts({
+        cranker: cranker.publicKey,
+        idendity: idendity,
+        tokenAccount: token_account,
+        payer: payer,
+      })
+      .signers([cranker])
+      .rpc();
+
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: owner.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    await program.methods
+      .addTokenAccount()
+      .accounts({
+        owner: owner.publicKey,
+        tokenAccount: token_account,
+        mint: args.mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+    // The idendity expires after one second
+    await program.methods
+      .addIdendity(new anchor.BN(1))
+      .accounts({
+        idendity: idendity,
+        parentIssuerRecord: null,
+        owner: owner.publicKey,
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        mint: args.mint,
+        tokenAccount: token_account,
+        previousHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          token_account
+        ),
+        consent: null,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([args.issuer])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    await prune(args.issuer.publicKey);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "NothingToPrune"
+    );
+  }
+
+  try {
+    await update_idendity("suspendIdentity");
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  await new Promise((resolve) => setTimeout(resolve, 2000));
+
+  try {
+    // Expired but suspended: the suspension must not be dropped
+    await prune(args.issuer.publicKey);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "NothingToPrune"
+    );
+  }
+
+  try {
+    await update_idendity("reinstateIdentity");
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  try {
+    // The rent goes back to the issuer that paid for the idendity
+    await prune(owner.publicKey);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "NotIdendityPayer"
+    );
+  }
+
+  const payer_balance = await connection.getBalance(args.issuer.publicKey);
+  try {
+    await prune(args.issuer.publicKey);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  expect(await connection.getAccountInfo(idendity)).to.be.null;
+  expect(await connection.getBalance(args.issuer.publicKey)).to.be.greaterThan(
+    payer_balance
+  );
+}
+
+// A person idendity is seeded by its owner: its balance is the balance of the token accounts it links (its holdings)
+export async function test_prune_person_idendity_holding_tokens(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number
+) {
+  const connection = program.provider.connection;
+  const owner = anchor.web3.Keypair.generate();
+  const minter = args.users[0].owner;
+  const token_account = getAssociatedTokenAddressSync(
+    args.mint,
+    owner.publicKey,
+    false,
+    TOKEN_2022_PROGRAM_ID
+  );
+  const [idendity] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("identity")),
+      owner.publicKey.toBuffer(),
+    ],
+    program.programId
+  );
+
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: owner.publicKey,
+          lamports: 0.05 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    await program.methods
+      .addTokenAccount()
+      .accounts({
+        owner: owner.publicKey,
+        tokenAccount: token_account,
+        mint: args.mint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+    // The idendity expires after one second
+    await program.methods
+      .addPersonIdentity(new anchor.BN(1))
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        parentIssuerRecord: null,
+        idendity: idendity,
+        owner: owner.publicKey,
+      })
+      .signers([args.issuer, owner])
+      .rpc();
+    await program.methods
+      .linkTokenAccount()
+      .accounts({
+        owner: owner.publicKey,
+        idendity: idendity,
+        mint: args.mint,
+        tokenAccount: token_account,
+        previousHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          token_account
+        ),
+        ...(await find_idendity_issuer_chain(program, idendity)),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([owner])
+      .rpc();
+    await program.methods
+      .mintTo(new anchor.BN(amount), null)
+      .accounts({
+        mint: args.mint,
+        toTokenAccount: token_account,
+        ...(await find_issuer_chain(program, token_account)),
+        minter: minter.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([minter])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  await new Promise((resolve) => setTimeout(resolve, 2000));
+
+  const prune = () =>
+    program.methods
+      .pruneExpiredIdentity()
+      .accounts({
+        cranker: args.admin.publicKey,
+        idendity: idendity,
+        tokenAccount: owner.publicKey,
+        payer: args.issuer.publicKey,
+      })
+      .signers([args.admin])
+      .rpc();
+
+  try {
+    // The owner key holds no token itself, the token account linked to the idendity does: the idendity is kept
+    // but its expired issuer is marked inactive
+    await prune();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const person = await program.account.idAccount.fetch(idendity);
+  expect(person.issuers[0].active).to.be.false;
+  expect(person.issuers[0].revoked).to.be.false;
+  expect(person.issuers[0].reasonCode).to.be.equal(65535);
+
+  try {
+    await prune();
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect((error as anchor.AnchorError).error.errorCode.code).to.be.equal(
+      "NothingToPrune"
+    );
+  }
+}
+
+// The Institution of a pruned institution idendity is closed with it, so that the entity can be issued an idendity again
+export async function test_prune_institution_idendity(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>
+) {
+  const connection = program.provider.connection;
+  const entity = anchor.web3.Keypair.generate();
+  const signer = anchor.web3.Keypair.generate();
+  const find_pda = (seed: string, key: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(anchor.utils.bytes.utf8.encode(seed)), key.toBuffer()],
+      program.programId
+    )[0];
+  const idendity = find_pda("identity", entity.publicKey);
+  const institution = find_pda("institution", entity.publicKey);
+  const add_institution_idendity = (validity: number) =>
+    program.methods
+      .addInstitutionIdentity(new anchor.BN(validity), [signer.publicKey])
+      .accounts({
+        issuer: args.issuer.publicKey,
+        issuerRecord: find_issuer_record(program, args.issuer.publicKey),
+        parentIssuerRecord: null,
+        idendity: idendity,
+        institution: institution,
+        entity: entity.publicKey,
+      })
+      .signers([args.issuer, entity])
+      .rpc();
+
+  try {
+    await anchor.web3.sendAndConfirmTransaction(
+      connection,
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: args.admin.publicKey,
+          toPubkey: entity.publicKey,
+          lamports: 0.1 * anchor.web3.LAMPORTS_PER_SOL,
+        })
+      ),
+      [args.admin]
+    );
+    // The idendity expires after one second
+    await add_institution_idendity(1);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  await new Promise((resolve) => setTimeout(resolve, 2000));
+
+  const entity_balance = await connection.getBalance(entity.publicKey);
+  try {
+    await program.methods
+      .pruneExpiredIdentity()
+      .accounts({
+        cranker: args.admin.publicKey,
+        idendity: idendity,
+        tokenAccount: entity.publicKey,
+        payer: args.issuer.publicKey,
+      })
+      .signers([args.admin])
+      .rpc();
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  expect(await connection.getAccountInfo(idendity)).to.be.null;
+  expect(await connection.getAccountInfo(institution)).to.be.null;
+  expect(await connection.getBalance(entity.publicKey)).to.be.greaterThan(
+    entity_balance
+  );
+
+  try {
+    await add_institution_idendity(100000);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  const account = await program.account.institution.fetch(institution);
+  expect(account.entity.toBase58()).to.be.equal(entity.publicKey.toBase58());
+}
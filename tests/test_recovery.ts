@@ -1,6 +1,10 @@
 import * as anchor from "@coral-xyz/anchor";
 import { UndefinedTemporary } from "../target/types/undefined_temporary";
 import { AccountArgs } from "./test_interfaces";
+import {
+  find_issuer_record,
+  find_linked_holdings,
+} from "./test_config";
 import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   TOKEN_2022_PROGRAM_ID,
@@ -55,6 +59,17 @@ export async function test_recovery(
         newTokenAccount: user2.token_account,
         newOwner: user2.owner.publicKey,
         mint: args.mint,
+        holdings: await find_linked_holdings(
+          program,
+          args.mint,
+          user1.token_account
+        ),
+        newHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          user2.token_account
+        ),
+        lockupPayer: null,
         recoveryAuthority: user1.recovery,
       })
       .remainingAccounts([
@@ -99,6 +114,17 @@ export async function test_recovery_missing_signers(
         newTokenAccount: user2.token_account,
         newOwner: user2.owner.publicKey,
         mint: args.mint,
+        holdings: await find_linked_holdings(
+          program,
+          args.mint,
+          user1.token_account
+        ),
+        newHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          user2.token_account
+        ),
+        lockupPayer: null,
         recoveryAuthority: user1.recovery,
       })
       .remainingAccounts([
@@ -136,6 +162,17 @@ export async function test_recovery_already_recovered(
         newTokenAccount: user2.token_account,
         newOwner: user2.owner.publicKey,
         mint: args.mint,
+        holdings: await find_linked_holdings(
+          program,
+          args.mint,
+          user1.token_account
+        ),
+        newHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          user2.token_account
+        ),
+        lockupPayer: null,
         recoveryAuthority: user1.recovery,
       })
       .remainingAccounts([
@@ -172,6 +209,17 @@ export async function test_recovery_more_signers(
         newTokenAccount: user3.token_account,
         newOwner: user3.owner.publicKey,
         mint: args.mint,
+        holdings: await find_linked_holdings(
+          program,
+          args.mint,
+          user2.token_account
+        ),
+        newHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          user3.token_account
+        ),
+        lockupPayer: null,
         recoveryAuthority: user2.recovery,
       })
       .remainingAccounts([
@@ -263,9 +311,17 @@ export async function test_recovery_without_close_authority(
       .addIdendity(new anchor.BN(1000))
       .accounts({
         idendity: pda_id_4,
+        parentIssuerRecord: null,
         owner: user4.publicKey,
         issuer: issuer.publicKey,
+        issuerRecord: find_issuer_record(program, issuer.publicKey),
         tokenAccount: tokenAccount,
+        previousHoldings: await find_linked_holdings(
+          program,
+          mint,
+          tokenAccount
+        ),
+        consent: null,
         tokenProgram: TOKEN_2022_PROGRAM_ID,
       })
       .signers([issuer])
@@ -303,6 +359,17 @@ export async function test_recovery_without_close_authority(
         newTokenAccount: user3.token_account,
         newOwner: user3.owner.publicKey,
         mint: args.mint,
+        holdings: await find_linked_holdings(
+          program,
+          args.mint,
+          tokenAccount
+        ),
+        newHoldings: await find_linked_holdings(
+          program,
+          args.mint,
+          user3.token_account
+        ),
+        lockupPayer: null,
         recoveryAuthority: recovery,
       })
       .remainingAccounts([
@@ -0,0 +1,215 @@
+import * as anchor from "@coral-xyz/anchor";
+import { UndefinedTemporary } from "../target/types/undefined_temporary";
+import { AccountArgs } from "./test_interfaces";
+import {
+  TOKEN_2022_PROGRAM_ID,
+  addExtraAccountMetasForExecute,
+  createExecuteInstruction,
+  createTransferCheckedWithTransferHookInstruction,
+  getAccount,
+} from "@solana/spl-token";
+import { PACKET_DATA_SIZE, sendAndConfirmTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+
+// Every account of TransferHook, resolved from the ExtraAccountMetaList like Token-2022 does for a transfer:
+// the call must only be rejected because no transfer is in progress
+async function transfer_hook_instruction(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  extra_account_meta_list: anchor.web3.PublicKey,
+  amount: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  const ix = createExecuteInstruction(
+    program.programId,
+    user1.token_account,
+    args.mint,
+    user2.token_account,
+    user1.owner.publicKey,
+    extra_account_meta_list,
+    BigInt(amount)
+  );
+  await addExtraAccountMetasForExecute(
+    program.provider.connection,
+    ix,
+    program.programId,
+    user1.token_account,
+    args.mint,
+    user2.token_account,
+    user1.owner.publicKey,
+    BigInt(amount),
+    "confirmed"
+  );
+  ix.data = program.coder.instruction.encode("transferHook", {
+    amount: new anchor.BN(amount),
+  });
+  return ix;
+}
+
+function expect_not_transferring(error: any) {
+  expect(
+    ((error as anchor.AnchorError).logs as Array<string>).some((log) =>
+      log.includes("Error Code: NotTransferring.")
+    )
+  ).to.be.true;
+}
+
+export async function test_direct_transfer_hook_call(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  extra_account_meta_list: anchor.web3.PublicKey,
+  amount: number
+) {
+  let user1 = args.users[0];
+  const last_tx_before = await program.account.lastTx.fetch(user1.last_tx);
+
+  try {
+    const ix = await transfer_hook_instruction(
+      args,
+      program,
+      extra_account_meta_list,
+      amount
+    );
+    const txSig = await sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(ix),
+      [user1.owner]
+    );
+    console.log("Direct transfer hook: NOT NORMAL", txSig);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect_not_transferring(error);
+  }
+
+  const last_tx_after = await program.account.lastTx.fetch(user1.last_tx);
+  expect(last_tx_after.lastTxTimestamp.toNumber()).to.be.equal(
+    last_tx_before.lastTxTimestamp.toNumber()
+  );
+}
+
+// Same call but through the transfer hook interface `Execute` instruction (routed by the fallback)
+export async function test_direct_execute_call(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  extra_account_meta_list: anchor.web3.PublicKey,
+  amount: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+
+  try {
+    const ix = await transfer_hook_instruction(
+      args,
+      program,
+      extra_account_meta_list,
+      amount
+    );
+    ix.data = createExecuteInstruction(
+      program.programId,
+      user1.token_account,
+      args.mint,
+      user2.token_account,
+      user1.owner.publicKey,
+      extra_account_meta_list,
+      BigInt(amount)
+    ).data;
+
+    const txSig = await sendAndConfirmTransaction(
+      program.provider.connection,
+      new anchor.web3.Transaction().add(ix),
+      [user1.owner]
+    );
+    console.log("Direct execute: NOT NORMAL", txSig);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect_not_transferring(error);
+  }
+}
+
+// A transfer carries every account of TransferHook, resolved by the client from the ExtraAccountMetaList:
+// the legacy transaction of a plain transfer must fit in a packet
+export async function test_transfer_size(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  const connection = program.provider.connection;
+  const balance = async () =>
+    (
+      await getAccount(
+        connection,
+        user2.token_account,
+        "confirmed",
+        TOKEN_2022_PROGRAM_ID
+      )
+    ).amount;
+  const balance_before = await balance();
+
+  const ix = await createTransferCheckedWithTransferHookInstruction(
+    connection,
+    user1.token_account,
+    args.mint,
+    user2.token_account,
+    user1.owner.publicKey,
+    BigInt(amount),
+    decimals,
+    [],
+    "confirmed",
+    TOKEN_2022_PROGRAM_ID
+  );
+  const tx = new anchor.web3.Transaction().add(ix);
+  tx.feePayer = user1.owner.publicKey;
+  tx.recentBlockhash = (await connection.getLatestBlockhash()).blockhash;
+  tx.sign(user1.owner);
+  expect(tx.serialize().length).to.be.at.most(PACKET_DATA_SIZE);
+
+  try {
+    await sendAndConfirmTransaction(connection, tx, [user1.owner]);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+  expect(await balance()).to.be.equal(balance_before + BigInt(amount));
+}
+
+export async function test_update_extra_account_meta_list(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  extra_account_meta_list: anchor.web3.PublicKey
+) {
+  const [mint_config] = anchor.web3.PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode("mint_config")),
+      args.mint.toBuffer(),
+    ],
+    program.programId
+  );
+
+  try {
+    const tx = await program.methods
+      .updateExtraAccountMetaList()
+      .accounts({
+        payer: args.admin.publicKey,
+        extraAccountMetaList: extra_account_meta_list,
+        mint: args.mint,
+        mintConfig: mint_config,
+      })
+      .signers([args.admin])
+      .rpc();
+
+    console.log("Your transaction signature for meta list update", tx);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  const version = program.idl.constants.find(
+    (constant) => constant.name === "EXTRA_ACCOUNT_METAS_VERSION"
+  ).value;
+  const config = await program.account.mintConfig.fetch(mint_config);
+  expect(config.extraAccountMetasVersion).to.be.equal(Number(version));
+}
@@ -5,8 +5,10 @@ import {
   ASSOCIATED_TOKEN_PROGRAM_ID,
   TOKEN_2022_PROGRAM_ID,
   createAssociatedTokenAccountInstruction,
+  createTransferCheckedWithTransferHookInstruction,
   getAssociatedTokenAddressSync,
 } from "@solana/spl-token";
+import { sendAndConfirmTransaction } from "@solana/web3.js";
 import { expect } from "chai";
 
 export async function test_2_auth_init(
@@ -40,3 +42,106 @@ export async function test_2_auth_init(
     console.log(error);
   }
 }
+
+async function transfer_with_hook(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number,
+  pre_instructions: anchor.web3.TransactionInstruction[],
+  signers: anchor.web3.Keypair[]
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  const transferInstructionWithHelper =
+    await createTransferCheckedWithTransferHookInstruction(
+      program.provider.connection,
+      user1.token_account,
+      args.mint,
+      user2.token_account,
+      user1.owner.publicKey,
+      BigInt(amount),
+      decimals,
+      [],
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+
+  const transaction = new anchor.web3.Transaction().add(
+    ...pre_instructions,
+    transferInstructionWithHelper
+  );
+  return await sendAndConfirmTransaction(
+    program.provider.connection,
+    transaction,
+    [user1.owner, ...signers]
+  );
+}
+
+export async function test_2_auth_approval_replay(
+  args: AccountArgs,
+  program: anchor.Program<UndefinedTemporary>,
+  amount: number,
+  decimals: number
+) {
+  let user1 = args.users[0];
+  let user2 = args.users[1];
+  let issuer = args.issuer;
+
+  try {
+    const approval = await program.methods
+      .approveTransaction({
+        source: user1.token_account,
+        destination: user2.token_account,
+        amount: new anchor.BN(amount),
+        time: new anchor.BN(Math.floor(Date.now() / 1000)),
+      })
+      .accounts({
+        owner: user1.owner.publicKey,
+        tokenAccount: user1.token_account,
+        approver: issuer.publicKey,
+        mint: args.mint,
+        twoAuthParameters: user1.two_auth,
+        transactionApproval: user1.approval,
+      })
+      .instruction();
+
+    const txSig = await transfer_with_hook(
+      args,
+      program,
+      amount,
+      decimals,
+      [approval],
+      [issuer]
+    );
+    console.log("Transfer Signature with approval", txSig);
+  } catch (error) {
+    console.log(error);
+    expect(error).to.be.undefined;
+  }
+
+  // The approval has been consumed by the transfer hook
+  const transaction_approval = await program.account.transactionAproval.fetch(
+    user1.approval
+  );
+  expect(transaction_approval.active).to.be.false;
+
+  try {
+    const txSig = await transfer_with_hook(
+      args,
+      program,
+      amount,
+      decimals,
+      [],
+      []
+    );
+    console.log("Transfer Signature: NOT NORMAL", txSig);
+    expect.fail("This test should fail");
+  } catch (error) {
+    expect(
+      ((error as anchor.AnchorError).logs as Array<string>).some((log) =>
+        log.includes("Error Code: InactiveApproval.")
+      )
+    ).to.be.true;
+  }
+}
@@ -15,6 +15,7 @@ import {
   getAssociatedTokenAddressSync,
   getExtraAccountMetaAddress,
   getExtraAccountMetas,
+  getAccount,
   getMint,
   getMintLen,
   getTransferHook,
@@ -32,7 +33,61 @@ import {
   test_recovery_without_close_authority,
 } from "./test_recovery";
 import { init_mint } from "./test_initialize_mint";
-import { test_2_auth_init } from "./test_two_auth";
+import {
+  test_2_auth_approval_replay,
+  test_2_auth_init,
+} from "./test_two_auth";
+import { test_paused_transfer } from "./test_pause";
+import {
+  test_prove_claim,
+  test_required_claims_transfer,
+  test_set_claim,
+} from "./test_claims";
+import { test_jurisdiction_matrix } from "./test_jurisdiction";
+import {
+  test_prune_expired_idendity,
+  test_prune_person_idendity_holding_tokens,
+  test_prune_institution_idendity,
+} from "./test_prune";
+import { init_deny_list, test_deny_list } from "./test_deny_list";
+import { test_balance_caps } from "./test_balance_caps";
+import { test_holder_recount, test_max_holders } from "./test_holder_count";
+import { test_lockup } from "./test_lockup";
+import {
+  test_add_attested_idendity,
+  test_add_remove_issuer,
+  test_owner_consent,
+  test_sub_issuer,
+  test_rotate_issuer_key,
+  test_validity_duration_limits,
+  test_person_idendity,
+  test_institution_idendity,
+  test_revoke_is_permanent,
+  test_suspend_reinstate_renew,
+  test_update_idendity_wrong_issuer,
+} from "./test_idendity";
+import {
+  test_direct_execute_call,
+  test_direct_transfer_hook_call,
+  test_transfer_size,
+  test_update_extra_account_meta_list,
+} from "./test_transfer_hook";
+import {
+  find_issuer_chain,
+  find_issuer_record,
+  find_linked_holdings,
+  grant_role,
+  init_config,
+  register_issuer,
+  test_grant_role_not_super_admin,
+  test_super_admin_handover,
+} from "./test_config";
+import {
+  test_compliance_freeze_before_issuance,
+  test_compliance_freeze_not_authority,
+  test_compliance_freeze_thaw,
+  test_forced_transfer,
+} from "./test_compliance";
 
 function sleep(ms: number) {
   return new Promise((resolve) => setTimeout(resolve, ms));
@@ -406,13 +461,61 @@ describe("undefined_temporary", () => {
       },
     ],
     issuer: issuer,
+    admin: wallet,
     mint: mint,
   };
 
+  it("Init Config", async () => {
+    await init_config(account_args, program);
+  });
+
+  it("Grant roles", async () => {
+    await grant_role(
+      account_args,
+      program,
+      { minterAdmin: {} },
+      user1.publicKey
+    );
+    await grant_role(
+      account_args,
+      program,
+      { issuerAdmin: {} },
+      user1.publicKey
+    );
+    await grant_role(
+      account_args,
+      program,
+      { compliance: {} },
+      user1.publicKey
+    );
+    await grant_role(
+      account_args,
+      program,
+      { pauser: {} },
+      user1.publicKey
+    );
+  });
+
+  it("Grant role fails if not super admin", async () => {
+    await test_grant_role_not_super_admin(account_args, program);
+  });
+
+  it("Super admin handover", async () => {
+    await test_super_admin_handover(account_args, program);
+  });
+
+  it("Register Issuer", async () => {
+    await register_issuer(account_args, program, user1);
+  });
+
   it("Create Mint", async () => {
     await init_mint(account_args, program);
   });
 
+  it("Create the deny list", async () => {
+    await init_deny_list(program, user1);
+  });
+
   it("Test Create Token Accounts", async () => {
     try {
       let tx = new anchor.web3.Transaction();
@@ -465,6 +568,15 @@ describe("undefined_temporary", () => {
     } catch (error) {
       console.log(error);
     }
+
+    // Default Account State: accounts are frozen until an idendity is issued
+    const token_account = await getAccount(
+      anchor.getProvider().connection,
+      sourceTokenAccount,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(token_account.isFrozen).to.be.true;
   });
 
   // Create the two token accounts for the transfer-hook enabled mint
@@ -514,29 +626,6 @@ describe("undefined_temporary", () => {
   //   }
   // });
 
-  it("Mint tokens", async () => {
-    // 100 tokens
-    const amount = 100 * 10 ** decimals;
-
-    try {
-      const tx = await program.methods
-        .mintTo(new anchor.BN(amount))
-        .accounts({
-          mint: mint,
-          toTokenAccount: sourceTokenAccount,
-          ownerTokenAccount: user1.publicKey,
-          tokenProgram: TOKEN_2022_PROGRAM_ID,
-        })
-        .signers([user1])
-        .rpc();
-
-      console.log(`Transaction Signature: ${tx}`);
-    } catch (error) {
-      console.log(error);
-      expect(error).to.be.undefined;
-    }
-  });
-
   it("Init ID", async () => {
     await sleep(1000);
     try {
@@ -544,9 +633,18 @@ describe("undefined_temporary", () => {
         .addIdendity(new anchor.BN(1000))
         .accounts({
           idendity: pda_id_1,
+          parentIssuerRecord: null,
           owner: user1.publicKey,
           issuer: issuer.publicKey,
+          issuerRecord: find_issuer_record(program, issuer.publicKey),
+          mint: mint,
           tokenAccount: sourceTokenAccount,
+          previousHoldings: await find_linked_holdings(
+            program,
+            mint,
+            sourceTokenAccount
+          ),
+          consent: null,
           tokenProgram: TOKEN_2022_PROGRAM_ID,
         })
         .signers([issuer])
@@ -556,9 +654,18 @@ describe("undefined_temporary", () => {
         .addIdendity(new anchor.BN(1000))
         .accounts({
           idendity: pda_id_2,
+          parentIssuerRecord: null,
           owner: user2.publicKey,
           issuer: issuer.publicKey,
+          issuerRecord: find_issuer_record(program, issuer.publicKey),
+          mint: mint,
           tokenAccount: destinationTokenAccount,
+          previousHoldings: await find_linked_holdings(
+            program,
+            mint,
+            destinationTokenAccount
+          ),
+          consent: null,
           tokenProgram: TOKEN_2022_PROGRAM_ID,
         })
         .signers([issuer])
@@ -568,6 +675,111 @@ describe("undefined_temporary", () => {
       console.log(error);
       expect(error).to.be.undefined;
     }
+
+    const token_account = await getAccount(
+      anchor.getProvider().connection,
+      sourceTokenAccount,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    expect(token_account.isFrozen).to.be.false;
+  });
+
+  it("Mint tokens", async () => {
+    // 100 tokens
+    const amount = 100 * 10 ** decimals;
+
+    try {
+      const tx = await program.methods
+        .mintTo(new anchor.BN(amount), null)
+        .accounts({
+          mint: mint,
+          toTokenAccount: sourceTokenAccount,
+          ...(await find_issuer_chain(program, sourceTokenAccount)),
+          minter: user1.publicKey,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([user1])
+        .rpc();
+
+      console.log(`Transaction Signature: ${tx}`);
+    } catch (error) {
+      console.log(error);
+      expect(error).to.be.undefined;
+    }
+  });
+
+  it("Suspend, reinstate and renew an idendity", async () => {
+    await test_suspend_reinstate_renew(account_args, program);
+  });
+
+  it("Only the issuer of an idendity can update it", async () => {
+    await test_update_idendity_wrong_issuer(account_args, program);
+  });
+
+  it("Add and remove an issuer of an idendity", async () => {
+    await register_issuer(account_args, program, user1, user1.publicKey);
+    await test_add_remove_issuer(account_args, program, user1);
+  });
+
+  it("Add an idendity from an attestation signed off-chain", async () => {
+    await test_add_attested_idendity(account_args, program);
+  });
+
+  it("Owner consent to the issuers of its idendity", async () => {
+    await test_owner_consent(account_args, program, user1);
+  });
+
+  it("Sub-issuers attest within their scope", async () => {
+    await test_sub_issuer(account_args, program);
+  });
+
+  it("Validity durations within the issuer and mint limits", async () => {
+    await test_validity_duration_limits(account_args, program);
+  });
+
+  it("One person idendity linked to its token accounts", async () => {
+    await test_person_idendity(account_args, program);
+  });
+
+  it("Institution idendity with authorized signers and program vaults", async () => {
+    await test_institution_idendity(account_args, program);
+  });
+
+  it("Prune an expired idendity and refund its rent", async () => {
+    await test_prune_expired_idendity(account_args, program);
+  });
+
+  it("A person idendity whose token accounts hold tokens is not pruned", async () => {
+    await test_prune_person_idendity_holding_tokens(
+      account_args,
+      program,
+      1 * 10 ** decimals
+    );
+  });
+
+  it("Prune an expired institution idendity and its institution", async () => {
+    await test_prune_institution_idendity(account_args, program);
+  });
+
+  it("Rotate the signing key of an issuer", async () => {
+    await test_rotate_issuer_key(account_args, program, user1);
+  });
+
+  it("Compliance freeze and thaw", async () => {
+    await test_compliance_freeze_thaw(account_args, program);
+  });
+
+  it("Compliance freeze of an account frozen by default", async () => {
+    await test_compliance_freeze_before_issuance(account_args, program);
+  });
+
+  it("Compliance freeze fails if not compliance authority", async () => {
+    await test_compliance_freeze_not_authority(account_args, program);
+  });
+
+  it("Forced transfer by the compliance authority", async () => {
+    await test_forced_transfer(account_args, program, 1 * 10 ** decimals);
   });
 
   it("Init recovery Account", async () => {
@@ -585,13 +797,13 @@ describe("undefined_temporary", () => {
       const tx = await program.methods
         .initializeExtraAccountMetaList()
         .accounts({
-          payer: user1.publicKey,
+          payer: wallet.publicKey,
           extraAccountMetaList: extraAccountMetaListPDA,
           mint: mint,
           tokenProgram: TOKEN_2022_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         })
-        .signers([user1])
+        .signers([wallet])
         .rpc();
 
       console.log("Transaction Signature:", tx);
@@ -601,6 +813,14 @@ describe("undefined_temporary", () => {
     }
   });
 
+  it("Update ExtraAccountMetaList Account", async () => {
+    await test_update_extra_account_meta_list(
+      account_args,
+      program,
+      extraAccountMetaListPDA
+    );
+  });
+
   sleep(2000);
 
   it("Transfer Hook with Extra Account Meta", async () => {
@@ -660,6 +880,102 @@ describe("undefined_temporary", () => {
       expect(error).to.be.undefined;
     }
   });
+
+  it("Two auth approval can only be used once", async () => {
+    await test_2_auth_approval_replay(
+      account_args,
+      program,
+      2 * 10 ** decimals,
+      decimals
+    );
+  });
+
+  it("Direct call to the transfer hook is rejected", async () => {
+    await test_direct_transfer_hook_call(
+      account_args,
+      program,
+      extraAccountMetaListPDA,
+      1 * 10 ** decimals
+    );
+  });
+
+  it("Direct Execute call through the fallback is rejected", async () => {
+    await test_direct_execute_call(
+      account_args,
+      program,
+      extraAccountMetaListPDA,
+      1 * 10 ** decimals
+    );
+  });
+
+  it("A transfer with every hook account fits in a transaction", async () => {
+    // Below the two auth threshold of user1
+    await test_transfer_size(account_args, program, 100, decimals);
+  });
+
+  it("Transfer and mint fail when the mint is paused", async () => {
+    await test_paused_transfer(
+      account_args,
+      program,
+      1 * 10 ** decimals,
+      decimals
+    );
+  });
+
+  it("Transfer fails when an address is on the deny list", async () => {
+    await test_deny_list(account_args, program, 1 * 10 ** decimals, decimals);
+  });
+
+  it("Transfer and mint fail above the balance cap of the receiver", async () => {
+    await test_balance_caps(
+      account_args,
+      program,
+      1 * 10 ** decimals,
+      decimals
+    );
+  });
+
+  it("No new holder once the maximum number of holders is reached", async () => {
+    await test_max_holders(account_args, program, 1 * 10 ** decimals);
+  });
+
+  it("Recount the holders", async () => {
+    await test_holder_recount(account_args, program);
+  });
+
+  it("Locked tokens can only be transferred once released", async () => {
+    await test_lockup(account_args, program, 100, decimals);
+  });
+
+  it("Issuer sets claims on an idendity", async () => {
+    await test_set_claim(account_args, program);
+  });
+
+  it("Transfer fails when the receiver misses a required claim", async () => {
+    await test_required_claims_transfer(
+      account_args,
+      program,
+      1 * 10 ** decimals,
+      decimals
+    );
+  });
+
+  it("Prove a claim committed in a Merkle root", async () => {
+    await test_prove_claim(account_args, program);
+  });
+
+  it("Transfer fails for blocked jurisdictions", async () => {
+    await test_jurisdiction_matrix(
+      account_args,
+      program,
+      1 * 10 ** decimals,
+      decimals
+    );
+  });
+
+  it("Revoking an idendity is permanent", async () => {
+    await test_revoke_is_permanent(account_args, program);
+  });
   return;
   it("Unauthorized Transaction without ID", async () => {
     // 1 tokens
@@ -694,7 +1010,7 @@ describe("undefined_temporary", () => {
       expect(txSig).to.be.undefined;
     } catch (error) {
       expect((error as anchor.AnchorError).logs as Array<string>).contain(
-        "Program log: AnchorError caused by account: idendity_receiver. Error Code: AccountNotInitialized. Error Number: 3012. Error Message: The program expected this account to be already initialized."
+        "Program log: AnchorError caused by account: link_receiver. Error Code: AccountNotInitialized. Error Number: 3012. Error Message: The program expected this account to be already initialized."
       );
     }
   });
@@ -705,17 +1021,28 @@ describe("undefined_temporary", () => {
 
     try {
       const tx = await program.methods
-        .addIdendity(new anchor.BN(-1))
+        .addIdendity(new anchor.BN(1))
         .accounts({
           idendity: pda_id_3,
+          parentIssuerRecord: null,
           owner: user3.publicKey,
           issuer: issuer.publicKey,
+          issuerRecord: find_issuer_record(program, issuer.publicKey),
+          mint: mint,
           tokenAccount: ThirdTokenAccount,
+          previousHoldings: await find_linked_holdings(
+            program,
+            mint,
+            ThirdTokenAccount
+          ),
+          consent: null,
           tokenProgram: TOKEN_2022_PROGRAM_ID,
         })
         .signers([issuer])
         .rpc();
       console.log("Your transaction signature for ID3", tx);
+      // The idendity expires after one second
+      await sleep(2000);
     } catch (error) {
       expect(error).to.be.undefined;
     }